@@ -0,0 +1,155 @@
+use crate::config::BinlogSettings;
+use crate::helpers::strip_port;
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use std::time::Duration;
+
+/// Binary log dump thread of the source, serving a connected replica
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct DumpThread {
+    pub id: i64,
+    /// Replica host, without the port
+    pub host: String,
+    /// Thread state, telling whether the replica has read every binary log
+    pub state: String,
+}
+
+impl DumpThread {
+    /// Binary log dump threads of the process list, the threads of other users need PROCESS
+    pub const SQL: &str = "SELECT CAST(id AS SIGNED) AS id, host, COALESCE(state, '') AS state \
+         FROM information_schema.processlist \
+         WHERE command IN ('Binlog Dump', 'Binlog Dump GTID') \
+         ORDER BY id;";
+
+    /// Read a thread from a row of `SQL`
+    pub fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let host: String = row.try_get("host")?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            host: strip_port(&host).to_string(),
+            state: row.try_get("state")?,
+        })
+    }
+
+    /// Returns true once the replica has read every binary log, the state is
+    /// `Source has sent all binlog to replica` on `MySQL` and `Master has sent all binlog to slave` before 8.0.26 and on `MariaDB`
+    #[inline]
+    #[must_use]
+    pub fn is_caught_up(&self) -> bool {
+        self.state.contains("sent all binlog")
+    }
+}
+
+/// Replicas of the source, checked before its binary logs are purged
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[must_use]
+pub struct ReplicaLinks {
+    /// Number of replicas registered on the source
+    pub registered: usize,
+    /// Dump threads of the connected replicas
+    pub threads: Vec<DumpThread>,
+}
+
+impl ReplicaLinks {
+    /// Why the binary logs must be kept, None if they may be purged
+    /// The position of a disconnected replica is unknown, and a connected one still reading old logs may need them
+    /// A replica down long enough to be unregistered is only seen through `expected_replicas`
+    /// The thread state stands in for the replica's position: only a caught-up replica has read every log
+    #[must_use]
+    pub fn keep_reason(&self, settings: &BinlogSettings) -> Option<String> {
+        let expected: usize = settings.expected_replicas.unwrap_or(self.registered).max(self.registered);
+        if self.threads.len() < expected {
+            return Some(format!("{} of the {expected} replicas are connected", self.threads.len()));
+        }
+        let behind: Vec<&str> = self
+            .threads
+            .iter()
+            .filter(|thread| !thread.is_caught_up())
+            .map(|thread| thread.host.as_str())
+            .collect();
+        if !behind.is_empty() {
+            return Some(format!("replicas still reading old binary logs: {}", behind.join(", ")));
+        }
+        None
+    }
+}
+
+/// Age of the oldest binary log kept, 7 days when not configured
+#[must_use]
+pub fn retention(settings: &BinlogSettings) -> Duration {
+    settings.retention.unwrap_or(Duration::from_secs(7 * 24 * 3600))
+}
+
+/// Statement purging the binary logs older than the retention
+#[must_use]
+pub fn purge_statement(settings: &BinlogSettings) -> String {
+    format!(
+        "PURGE BINARY LOGS BEFORE DATE_SUB(NOW(), INTERVAL {} SECOND)",
+        retention(settings).as_secs()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thread(host: &str, state: &str) -> DumpThread {
+        DumpThread {
+            id: 7,
+            host: host.to_string(),
+            state: state.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_reason() {
+        let caught_up = "Source has sent all binlog to replica; waiting for more updates";
+        let links = ReplicaLinks {
+            registered: 2,
+            threads: vec![
+                thread("10.0.0.2", caught_up),
+                thread("10.0.0.3", "Master has sent all binlog to slave; waiting for binlog to be updated"),
+            ],
+        };
+        let settings = BinlogSettings::default();
+        assert_eq!(links.keep_reason(&settings), None);
+        assert_eq!(ReplicaLinks::default().keep_reason(&settings), None);
+
+        let lagging = ReplicaLinks {
+            registered: 1,
+            threads: vec![thread("10.0.0.2", "Sending binlog event to replica")],
+        };
+        assert_eq!(
+            lagging.keep_reason(&settings),
+            Some(String::from("replicas still reading old binary logs: 10.0.0.2"))
+        );
+
+        let expected = BinlogSettings {
+            expected_replicas: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(links.keep_reason(&expected), Some(String::from("2 of the 3 replicas are connected")));
+        let disconnected = ReplicaLinks {
+            registered: 1,
+            threads: Vec::new(),
+        };
+        assert_eq!(
+            disconnected.keep_reason(&settings),
+            Some(String::from("0 of the 1 replicas are connected"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purge_statement() {
+        assert_eq!(
+            purge_statement(&BinlogSettings::default()),
+            "PURGE BINARY LOGS BEFORE DATE_SUB(NOW(), INTERVAL 604800 SECOND)"
+        );
+        let settings = BinlogSettings {
+            retention: Some(Duration::from_secs(48 * 3600)),
+            ..Default::default()
+        };
+        assert_eq!(retention(&settings), Duration::from_secs(172_800));
+    }
+}
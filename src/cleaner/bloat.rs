@@ -0,0 +1,182 @@
+use crate::config::BloatThresholds;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+
+/// Size and dead tuple estimate of a `PostgreSQL` table
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct TableBloat {
+    pub schema: String,
+    pub table: String,
+    pub live_tuples: i64,
+    pub dead_tuples: i64,
+    pub table_bytes: i64,
+    /// Size the table would have once rewritten, None when the table has no statistics
+    pub expected_bytes: Option<i64>,
+}
+
+/// Vacuum strategy chosen for a table from its bloat estimate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VacuumAction {
+    /// Rewrite the table with VACUUM FULL to give the wasted space back to the system
+    Full,
+    /// Plain VACUUM to make dead tuples reusable without locking the table
+    Plain,
+    /// Nothing worth cleaning
+    Skip,
+}
+
+impl TableBloat {
+    /// Estimate of every user table in the schemas bound to `$1`, or in all non-system schemas when it is NULL
+    /// The expected size is computed from the live tuples and the average row width in `pg_stats`
+    pub const ESTIMATE_SQL: &str = "SELECT s.schemaname::TEXT AS schemaname, s.relname::TEXT AS tablename, \
+         s.n_live_tup::BIGINT AS live_tuples, s.n_dead_tup::BIGINT AS dead_tuples, \
+         pg_relation_size(s.relid)::BIGINT AS table_bytes, \
+         CASE WHEN w.row_width IS NULL THEN NULL ELSE (CEIL(s.n_live_tup * (24 + w.row_width) \
+         / (current_setting('block_size')::INT - 24)) * current_setting('block_size')::INT)::BIGINT END AS expected_bytes \
+         FROM pg_stat_user_tables s \
+         LEFT JOIN (SELECT schemaname, tablename, SUM((1 - null_frac) * avg_width) AS row_width \
+         FROM pg_stats GROUP BY schemaname, tablename) w \
+         ON w.schemaname = s.schemaname AND w.tablename = s.relname \
+         WHERE CASE WHEN $1::TEXT[] IS NULL THEN s.schemaname NOT IN ('pg_catalog', 'information_schema') \
+         ELSE s.schemaname = ANY($1) END \
+         ORDER BY s.schemaname, s.relname;";
+
+    /// Read an estimate from a row of `estimate_sql`
+    pub fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            schema: row.try_get("schemaname")?,
+            table: row.try_get("tablename")?,
+            live_tuples: row.try_get("live_tuples")?,
+            dead_tuples: row.try_get("dead_tuples")?,
+            table_bytes: row.try_get("table_bytes")?,
+            expected_bytes: row.try_get("expected_bytes")?,
+        })
+    }
+
+    /// Share of dead tuples in the table
+    #[must_use]
+    pub fn dead_tuple_ratio(&self) -> f64 {
+        let total: i64 = self.live_tuples + self.dead_tuples;
+        if total <= 0 {
+            return 0.0;
+        }
+        self.dead_tuples as f64 / total as f64
+    }
+
+    /// Estimated number of bytes a rewrite would give back
+    #[must_use]
+    pub fn wasted_bytes(&self) -> i64 {
+        self.expected_bytes
+            .map_or(0, |expected| (self.table_bytes - expected).max(0))
+    }
+
+    /// Share of the table size that is wasted
+    #[must_use]
+    pub fn bloat_ratio(&self) -> f64 {
+        if self.table_bytes <= 0 {
+            return 0.0;
+        }
+        self.wasted_bytes() as f64 / self.table_bytes as f64
+    }
+
+    /// Returns true if the share of dead tuples is worth a plain VACUUM
+    #[must_use]
+    pub fn has_dead_tuples(&self, thresholds: &BloatThresholds) -> bool {
+        self.dead_tuples > 0 && self.dead_tuple_ratio() >= thresholds.min_dead_tuple_ratio
+    }
+
+    /// Choose how the table should be vacuumed
+    #[must_use]
+    pub fn vacuum_action(&self, thresholds: &BloatThresholds) -> VacuumAction {
+        if self.wasted_bytes() >= thresholds.min_wasted_bytes
+            && self.bloat_ratio() >= thresholds.min_bloat_ratio
+        {
+            VacuumAction::Full
+        } else if self.has_dead_tuples(thresholds) {
+            VacuumAction::Plain
+        } else {
+            VacuumAction::Skip
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_bloat(dead_tuples: i64, table_bytes: i64, expected_bytes: Option<i64>) -> TableBloat {
+        TableBloat {
+            schema: String::from("public"),
+            table: String::from("users"),
+            live_tuples: 1000 - dead_tuples,
+            dead_tuples,
+            table_bytes,
+            expected_bytes,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bloat_ratios() {
+        let bloat = get_test_bloat(250, 1000, Some(600));
+        assert!((bloat.dead_tuple_ratio() - 0.25).abs() < f64::EPSILON);
+        assert_eq!(bloat.wasted_bytes(), 400);
+        assert!((bloat.bloat_ratio() - 0.4).abs() < f64::EPSILON);
+
+        let never_analyzed = get_test_bloat(0, 1000, None);
+        assert_eq!(never_analyzed.wasted_bytes(), 0);
+        assert_eq!(never_analyzed.dead_tuple_ratio(), 0.0);
+
+        let empty = TableBloat {
+            live_tuples: 0,
+            ..get_test_bloat(0, 0, Some(8192))
+        };
+        assert_eq!(empty.wasted_bytes(), 0);
+        assert_eq!(empty.bloat_ratio(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_action() {
+        let thresholds = BloatThresholds {
+            min_dead_tuple_ratio: 0.2,
+            min_bloat_ratio: 0.3,
+            min_wasted_bytes: 100,
+        };
+        assert_eq!(get_test_bloat(10, 1000, Some(500)).vacuum_action(&thresholds), VacuumAction::Full);
+        assert_eq!(get_test_bloat(300, 1000, Some(950)).vacuum_action(&thresholds), VacuumAction::Plain);
+        assert!(get_test_bloat(300, 1000, Some(500)).has_dead_tuples(&thresholds));
+        assert!(!get_test_bloat(10, 1000, Some(500)).has_dead_tuples(&thresholds));
+        assert_eq!(get_test_bloat(10, 1000, Some(950)).vacuum_action(&thresholds), VacuumAction::Skip);
+        // Large ratio but too few bytes to be worth a rewrite
+        assert_eq!(get_test_bloat(0, 150, Some(80)).vacuum_action(&thresholds), VacuumAction::Skip);
+    }
+
+    #[tokio::test]
+    async fn test_threshold_edges() {
+        let thresholds = BloatThresholds {
+            min_dead_tuple_ratio: 0.2,
+            min_bloat_ratio: 0.3,
+            min_wasted_bytes: 300,
+        };
+        // The dead tuple ratio is inclusive
+        assert!(get_test_bloat(200, 1000, None).has_dead_tuples(&thresholds));
+        assert!(!get_test_bloat(199, 1000, None).has_dead_tuples(&thresholds));
+        assert_eq!(get_test_bloat(200, 1000, None).vacuum_action(&thresholds), VacuumAction::Plain);
+        assert_eq!(get_test_bloat(199, 1000, None).vacuum_action(&thresholds), VacuumAction::Skip);
+
+        // A table without dead tuples never needs a plain VACUUM, even with a zero ratio threshold
+        let any_ratio = BloatThresholds {
+            min_dead_tuple_ratio: 0.0,
+            ..thresholds
+        };
+        assert!(!get_test_bloat(0, 1000, None).has_dead_tuples(&any_ratio));
+        assert!(get_test_bloat(1, 1000, None).has_dead_tuples(&any_ratio));
+
+        // Both the wasted bytes and the bloat ratio are inclusive, and both are needed for a VACUUM FULL
+        assert_eq!(get_test_bloat(0, 1000, Some(700)).vacuum_action(&thresholds), VacuumAction::Full);
+        assert_eq!(get_test_bloat(0, 1000, Some(701)).vacuum_action(&thresholds), VacuumAction::Skip);
+        assert_eq!(get_test_bloat(0, 999, Some(700)).vacuum_action(&thresholds), VacuumAction::Skip);
+        assert_eq!(get_test_bloat(200, 1000, Some(701)).vacuum_action(&thresholds), VacuumAction::Plain);
+    }
+}
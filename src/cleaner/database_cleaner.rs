@@ -1,14 +1,21 @@
+use crate::cleaner::report::CleanReport;
 use crate::config::Config;
 use async_trait::async_trait;
 use std::error::Error;
 
 #[async_trait]
-pub trait DatabaseCleaner {
+pub trait DatabaseCleaner: Send + Sync {
     /// Clean the database
     /// # Returns
-    /// * A Result containing the size of the database in bytes
+    /// * A Result containing the report of every step executed on the database
+    /// * A Box<dyn Error> object if the database could not be cleaned at all
+    async fn clean(&self) -> Result<CleanReport, Box<dyn Error>>;
+
+    /// Connect to the database and print information about the server without modifying it
+    /// # Returns
+    /// * A Result containing nothing if the server could be inspected
     /// * A Box<dyn Error> object
-    async fn clean(&self) -> Result<(), Box<dyn Error>>;
+    async fn inspect(&self) -> Result<(), Box<dyn Error>>;
 
     /// Load from a Config object
     /// # Arguments
@@ -25,12 +32,15 @@ mod tests {
     use super::*;
     use crate::config::ConnectionEngine;
 
-    #[allow(dead_code)]
     struct DummyCleaner(Config);
 
     #[async_trait]
     impl DatabaseCleaner for DummyCleaner {
-        async fn clean(&self) -> Result<(), Box<dyn Error>> {
+        async fn clean(&self) -> Result<CleanReport, Box<dyn Error>> {
+            Ok(CleanReport::new(&self.0).finish())
+        }
+
+        async fn inspect(&self) -> Result<(), Box<dyn Error>> {
             Ok(())
         }
 
@@ -50,8 +60,12 @@ mod tests {
             host: "localhost".to_string(),
             port: "5432".to_string(),
             schema: "test".to_string(),
+            ..Default::default()
         };
         let cleaner = DummyCleaner::from_config(config);
-        assert!(cleaner.clean().await.is_ok());
+        let report = cleaner.clean().await.unwrap();
+        assert!(report.is_healthy());
+        assert!(report.steps.is_empty());
+        assert!(cleaner.inspect().await.is_ok());
     }
 }
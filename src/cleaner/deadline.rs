@@ -0,0 +1,125 @@
+use crate::config::Config;
+use std::time::{Duration, Instant};
+
+/// Moment after which a cleaner must not start new operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct Deadline {
+    at: Option<Instant>,
+    reason: &'static str,
+}
+
+impl Deadline {
+    /// A deadline that is never reached
+    pub const NONE: Self = Self {
+        at: None,
+        reason: "",
+    };
+
+    /// Compute the deadline of a database from its maintenance window and maximum duration
+    pub fn from_config(config: &Config) -> Self {
+        let now: Instant = Instant::now();
+        let mut deadline: Self = Self::NONE;
+
+        if let Some(window) = &config.maintenance_window {
+            match window.remaining(chrono::Utc::now()) {
+                Some(remaining) => deadline = Self::after(now, remaining, "maintenance window ended"),
+                None => {
+                    return Self {
+                        at: Some(now),
+                        reason: "outside of the maintenance window",
+                    };
+                }
+            }
+        }
+
+        if let Some(max_duration) = config.max_duration {
+            let max_deadline: Self = Self::after(now, max_duration, "max_duration reached");
+            if deadline.at.is_none_or(|at| max_deadline.at.is_some_and(|max_at| max_at < at)) {
+                deadline = max_deadline;
+            }
+        }
+
+        deadline
+    }
+
+    /// A deadline reached after the given duration
+    fn after(now: Instant, duration: Duration, reason: &'static str) -> Self {
+        Self {
+            at: now.checked_add(duration),
+            reason,
+        }
+    }
+
+    /// Returns true if no new operation may be started
+    #[inline]
+    #[must_use]
+    pub fn is_reached(&self) -> bool {
+        self.at.is_some_and(|at| Instant::now() >= at)
+    }
+
+    /// The moment of the deadline, if any
+    #[inline]
+    #[must_use]
+    pub const fn instant(&self) -> Option<Instant> {
+        self.at
+    }
+
+    /// Why the deadline is reached, used as the reason of skipped operations
+    #[inline]
+    #[must_use]
+    pub const fn reason(&self) -> &'static str {
+        self.reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConnectionEngine, MaintenanceWindow, tests::get_test_config};
+    use chrono::{FixedOffset, NaiveTime, Timelike, Utc};
+
+    #[test]
+    fn test_deadline_none() {
+        let config = get_test_config(ConnectionEngine::Postgres, "5432");
+        let deadline = Deadline::from_config(&config);
+        assert_eq!(deadline, Deadline::NONE);
+        assert!(!deadline.is_reached());
+    }
+
+    #[test]
+    fn test_deadline_max_duration() {
+        let mut config = get_test_config(ConnectionEngine::Postgres, "5432");
+        config.max_duration = Some(Duration::ZERO);
+        let deadline = Deadline::from_config(&config);
+        assert!(deadline.is_reached());
+        assert_eq!(deadline.reason(), "max_duration reached");
+
+        config.max_duration = Some(Duration::from_secs(3600));
+        assert!(!Deadline::from_config(&config).is_reached());
+    }
+
+    #[test]
+    fn test_deadline_outside_window() {
+        let hour: u32 = Utc::now().hour();
+        let mut config = get_test_config(ConnectionEngine::Mysql, "3306");
+        config.maintenance_window = Some(MaintenanceWindow {
+            start: NaiveTime::from_hms_opt((hour + 2) % 24, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt((hour + 3) % 24, 0, 0).unwrap(),
+            offset: FixedOffset::east_opt(0),
+        });
+        let deadline = Deadline::from_config(&config);
+        assert!(deadline.is_reached());
+        assert_eq!(deadline.reason(), "outside of the maintenance window");
+
+        config.maintenance_window = Some(MaintenanceWindow {
+            start: NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt((hour + 2) % 24, 0, 0).unwrap(),
+            offset: FixedOffset::east_opt(0),
+        });
+        config.max_duration = Some(Duration::from_secs(60));
+        let deadline = Deadline::from_config(&config);
+        assert!(!deadline.is_reached());
+        assert_eq!(deadline.reason(), "max_duration reached");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::config::KillConnectionSettings;
+use crate::helpers::strip_port;
+use sqlx::mysql::MySqlRow;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use std::time::Duration;
+
+/// Idle client connection that the `kill_connections` step may kill
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct IdleConnection {
+    /// Connection id on `MySQL`, backend pid on `PostgreSQL`
+    pub id: i64,
+    pub user: String,
+    /// Client host, without the port
+    pub host: String,
+    /// Current database of the connection, if any
+    pub database: Option<String>,
+    /// Seconds since the connection became idle
+    pub idle_seconds: i64,
+}
+
+impl IdleConnection {
+    /// Connections sleeping for at least `?` seconds, except the one running the query
+    pub const MYSQL_SQL: &str = "SELECT CAST(id AS SIGNED) AS id, user, host, db, CAST(time AS SIGNED) AS idle_seconds \
+         FROM information_schema.processlist \
+         WHERE command = 'Sleep' AND time >= ? AND id <> CONNECTION_ID() \
+         ORDER BY time DESC;";
+
+    /// Sessions of the current database idle in a transaction for at least `$1` seconds, except the current one
+    pub const POSTGRES_SQL: &str = "SELECT pid::BIGINT AS id, COALESCE(usename::TEXT, '') AS user, \
+         COALESCE(host(client_addr), 'localhost') AS host, datname::TEXT AS db, \
+         EXTRACT(EPOCH FROM NOW() - state_change)::BIGINT AS idle_seconds \
+         FROM pg_stat_activity \
+         WHERE state IN ('idle in transaction', 'idle in transaction (aborted)') \
+         AND datname = current_database() AND pid <> pg_backend_pid() \
+         AND state_change <= NOW() - $1::BIGINT * INTERVAL '1 second' \
+         ORDER BY state_change;";
+
+    /// Minimum idle time of a killed connection, 1 hour when not configured
+    #[must_use]
+    pub fn min_idle(settings: &KillConnectionSettings) -> Duration {
+        settings.min_idle.unwrap_or(Duration::from_secs(3600))
+    }
+
+    /// Read a connection from a row of `MYSQL_SQL`, where the host carries the client port
+    pub fn from_mysql_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let host: String = row.try_get("host")?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            user: row.try_get("user")?,
+            host: strip_port(&host).to_string(),
+            database: row.try_get("db")?,
+            idle_seconds: row.try_get("idle_seconds")?,
+        })
+    }
+
+    /// Read a connection from a row of `POSTGRES_SQL`
+    pub fn from_pg_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            user: row.try_get("user")?,
+            host: row.try_get("host")?,
+            database: row.try_get("db")?,
+            idle_seconds: row.try_get("idle_seconds")?,
+        })
+    }
+
+    /// Why the connection must be kept, None if it may be killed
+    /// The connections of the cleaner's own user are always kept, they may belong to its pool
+    #[must_use]
+    pub fn keep_reason(&self, settings: &KillConnectionSettings, own_user: &str) -> Option<&'static str> {
+        if self.user == own_user {
+            return Some("opened by the cleaner's user");
+        }
+        if !settings.users.matches(&self.user) {
+            return Some("not selected by the user patterns");
+        }
+        if !settings.hosts.matches(&self.host) {
+            return Some("not selected by the host patterns");
+        }
+        if !settings.databases.matches(self.database.as_deref().unwrap_or_default()) {
+            return Some("not selected by the database patterns");
+        }
+        None
+    }
+
+    /// Description of the connection recorded in the report once killed
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let database: String = self
+            .database
+            .as_deref()
+            .map(|database| format!(" on {database}"))
+            .unwrap_or_default();
+        format!(
+            "connection {} ({}@{}{database}, idle for {}s)",
+            self.id, self.user, self.host, self.idle_seconds
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NameFilter;
+
+    fn connection(user: &str, host: &str, database: Option<&str>) -> IdleConnection {
+        IdleConnection {
+            id: 42,
+            user: user.to_string(),
+            host: host.to_string(),
+            database: database.map(str::to_string),
+            idle_seconds: 7200,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_reason() {
+        let settings = KillConnectionSettings {
+            users: NameFilter {
+                include: Vec::new(),
+                exclude: vec![String::from("pgbouncer*")],
+            },
+            hosts: NameFilter {
+                include: vec![String::from("10.0.*")],
+                exclude: Vec::new(),
+            },
+            databases: NameFilter {
+                include: Vec::new(),
+                exclude: vec![String::from("billing")],
+            },
+            ..Default::default()
+        };
+        assert_eq!(connection("app", "10.0.0.5", Some("shop")).keep_reason(&settings, "cleaner"), None);
+        assert_eq!(connection("app", "10.0.0.5", None).keep_reason(&settings, "cleaner"), None);
+        assert!(connection("cleaner", "10.0.0.5", Some("shop")).keep_reason(&settings, "cleaner").is_some());
+        assert!(connection("pgbouncer_rw", "10.0.0.5", Some("shop")).keep_reason(&settings, "cleaner").is_some());
+        assert!(connection("app", "192.168.1.2", Some("shop")).keep_reason(&settings, "cleaner").is_some());
+        assert!(connection("app", "10.0.0.5", Some("billing")).keep_reason(&settings, "cleaner").is_some());
+        assert_eq!(IdleConnection::min_idle(&settings), Duration::from_secs(3600));
+    }
+
+    #[tokio::test]
+    async fn test_describe() {
+        assert_eq!(
+            connection("app", "localhost", Some("shop")).describe(),
+            "connection 42 (app@localhost on shop, idle for 7200s)"
+        );
+    }
+}
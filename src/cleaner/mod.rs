@@ -1,4 +1,14 @@
 pub mod mysql;
 pub mod postgres;
 
-pub mod database_cleaner;
\ No newline at end of file
+pub mod binlog;
+pub mod bloat;
+pub mod database_cleaner;
+pub mod deadline;
+pub mod idle_connection;
+pub mod pool;
+pub mod preflight;
+pub mod replication_slot;
+pub mod report;
+pub mod server_version;
+pub mod table_status;
@@ -1,61 +1,144 @@
+use crate::cleaner::binlog::{purge_statement, DumpThread, ReplicaLinks};
 use crate::cleaner::database_cleaner::DatabaseCleaner;
+use crate::cleaner::deadline::Deadline;
+use crate::cleaner::idle_connection::IdleConnection;
+use crate::cleaner::pool::connect_mysql;
+use crate::cleaner::preflight::{MySqlGrants, Preflight};
+use crate::cleaner::report::{CleanReport, StepReport, StepStatus, LOCKED};
+use crate::cleaner::server_version::MySqlVersion;
+use crate::cleaner::table_status::{TableEngine, TableStatus};
 use crate::colors::{BLUE, RESET, YELLOW};
-use crate::config::Config;
-use crate::helpers::{get_url_connection, log_report, merge_schema};
-use crate::logger::{log_and_print, LogType};
+use crate::config::{Config, KillConnectionSettings, Operation};
+use crate::helpers::{
+    execute_concurrently, get_url_connection, is_lock_timeout, log_report, split_schema, warn_discarded_state,
+};
+use crate::logger::{log_and_print, print_message, LogType};
 use async_trait::async_trait;
 use num_format::{Locale, ToFormattedString};
 use sqlx::mysql::MySqlRow;
+use sqlx::pool::PoolConnection;
 use sqlx::{Executor, MySql, Pool, Row};
+use std::collections::HashSet;
 use std::error::Error;
+use std::sync::OnceLock;
+use std::time::Instant;
 
 #[non_exhaustive]
 pub struct MySQLCleaner {
     pub config: Config,
+    deadline: Deadline,
+    /// Flavor and version of the server, detected once connected
+    server: OnceLock<MySqlVersion>,
+    /// Why the server is a replica, None for a primary, detected once connected
+    replica: OnceLock<Option<String>>,
 }
 
 #[async_trait]
 impl DatabaseCleaner for MySQLCleaner {
-    async fn clean(&self) -> Result<(), Box<dyn Error>> {
+    async fn clean(&self) -> Result<CleanReport, Box<dyn Error>> {
         let database_url: String = get_url_connection(&self.config, &self.config.schema)?;
 
-        let pool: Pool<MySql> = Pool::connect(&database_url).await?;
-        println!("Cleaning {} database...", self.config.driver);
-        let start_bytes_size: i64 = Self::get_size_of_database(&pool).await.unwrap_or(0);
+        let pool: Pool<MySql> = connect_mysql(&self.config, &database_url).await?;
+        print_message(&format!("Cleaning {} database...", self.config.driver));
+        let mut report: CleanReport = CleanReport::new(&self.config);
+        if let Some(warning) = self.detect_server(&pool).await {
+            report.warn(warning);
+        }
+        if let Some(warning) = self.detect_replica(&pool).await {
+            report.warn(warning);
+        }
+        report.start_bytes = Self::get_size_of_database(&pool).await.unwrap_or(0);
 
-        println!(
+        print_message(&format!(
             "Size of database at start: {BLUE}{}{RESET} bytes",
-            start_bytes_size.to_formatted_string(&Locale::en)
-        );
-
-        println!("Cleaning temporary tables and connections...");
-        self.clean_temporary_objects(&pool).await?;
+            report.start_bytes.to_formatted_string(&Locale::en)
+        ));
 
-        println!("Optimizing all tables (defrag + analyze + repair)...");
-        self.optimize_all_tables(&pool).await?;
-
-        println!("Checking and repairing tables if needed...");
-        self.check_and_repair_tables(&pool).await?;
+        // Tables are planned once per run, so that each of them is rewritten at most once
+        // The error is not Send, so it must not be held while the pool closes
+        let tables: Vec<TableStatus> = match self.get_table_status(&pool).await.map_err(|e| e.to_string()) {
+            Ok(tables) => tables,
+            Err(e) => {
+                pool.close().await;
+                return Err(e.into());
+            }
+        };
+        // Nothing is modified before the privileges are checked
+        match self.preflight(&pool, &tables).await {
+            Ok(preflight) => {
+                preflight.print();
+                report.set_preflight(preflight);
+            }
+            Err(e) => report.warn(format!("Preflight failed: {e}")),
+        }
+        warn_discarded_state(&self.config);
 
-        println!("Rebuilding indexes for InnoDB tables...");
-        self.reindex_all_tables(&pool).await?;
+        let mut rebuilt: HashSet<String> = HashSet::new();
+        let first_step: usize = report.steps.len();
 
-        println!("Updating table statistics...");
-        self.analyse_all_tables(&pool).await?;
+        for operation in Self::OPERATIONS {
+            if !self.config.is_operation_enabled(operation) {
+                report.add_step(StepReport::skipped(operation, None, "disabled in configuration"));
+                continue;
+            }
+            if let Some(reason) = self.replica_skip_reason() {
+                report.add_step(StepReport::skipped(operation, None, &reason));
+                continue;
+            }
+            if self.deadline.is_reached() {
+                log_and_print(
+                    &format!("Skipping {operation}: {}", self.deadline.reason()),
+                    &LogType::Warning,
+                );
+                report.add_step(StepReport::skipped(operation, None, self.deadline.reason()));
+                continue;
+            }
+            if let Some(reason) = self.online_skip_reason(operation) {
+                print_message(&format!("Skipping {operation}: {reason}"));
+                report.add_step(StepReport::skipped(operation, None, reason));
+                continue;
+            }
+            let mut step: StepReport = StepReport::new(operation, None);
+            let result = self
+                .run_step(&pool, operation, &tables, &mut rebuilt, &mut step)
+                .await;
+            report.add_step(step.finish(result));
+        }
 
-        println!("Flushing caches and logs...");
-        Self::flush_caches(&pool).await?;
+        if self.config.retry_locked {
+            self.retry_locked(&pool, &mut report, first_step).await;
+        }
 
-        println!("Purging old binary and slow query logs...");
-        Self::purge_logs(&pool).await?;
+        report.end_bytes = Self::get_size_of_database(&pool).await.unwrap_or(0);
 
-        println!("Resetting statistics...");
-        Self::reset_statistics(&pool).await?;
+        log_report(report.start_bytes, report.end_bytes);
 
-        let end_bytes_size: i64 = Self::get_size_of_database(&pool).await.unwrap_or(0);
+        pool.close().await;
+        Ok(report.finish())
+    }
 
-        log_report(start_bytes_size, end_bytes_size);
+    async fn inspect(&self) -> Result<(), Box<dyn Error>> {
+        let database_url: String = get_url_connection(&self.config, &self.config.schema)?;
 
+        let pool: Pool<MySql> = connect_mysql(&self.config, &database_url).await?;
+        if let Some(warning) = self.detect_server(&pool).await {
+            log_and_print(&warning, &LogType::Warning);
+        }
+        if let Some(warning) = self.detect_replica(&pool).await {
+            log_and_print(&warning, &LogType::Warning);
+        }
+        let size: i64 = Self::get_size_of_database(&pool).await.unwrap_or(0);
+        let tables: Vec<TableStatus> = self.get_table_status(&pool).await.unwrap_or_default();
+        let preflight: Preflight = self.preflight(&pool, &tables).await?;
+
+        print_message(&format!(
+            "Size of database: {BLUE}{}{RESET} bytes",
+            size.to_formatted_string(&Locale::en)
+        ));
+        print_message(&format!("Tables to clean: {BLUE}{}{RESET}", tables.len()));
+        preflight.print();
+
+        pool.close().await;
         Ok(())
     }
 
@@ -66,92 +149,426 @@ impl DatabaseCleaner for MySQLCleaner {
 
 impl MySQLCleaner {
     /// Create a new instance of `MySQLCleaner` with the given configuration
+    /// The deadline of the maintenance window and `max_duration` starts now
     #[inline]
     #[must_use]
-    pub const fn new(config: Config) -> Self {
-        Self { config }
+    pub fn new(config: Config) -> Self {
+        let deadline: Deadline = Deadline::from_config(&config);
+        Self {
+            config,
+            deadline,
+            server: OnceLock::new(),
+            replica: OnceLock::new(),
+        }
     }
 
-    /// Clean temporary tables and kill sleeping connections
+    /// Flavor and version of the server, or the configured driver before it is detected
     #[inline]
-    async fn clean_temporary_objects(&self, pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
+    fn server(&self) -> MySqlVersion {
+        self.server
+            .get()
+            .copied()
+            .unwrap_or_else(|| MySqlVersion::unknown(&self.config.driver))
+    }
+
+    /// Detect the flavor and version of the server, returning a warning if they could not be used
+    async fn detect_server(&self, pool: &Pool<MySql>) -> Option<String> {
+        let (version, comment): (String, String) =
+            match sqlx::query_as("SELECT VERSION(), @@version_comment").fetch_one(pool).await {
+                Ok(row) => row,
+                Err(e) => {
+                    return Some(format!(
+                        "Server version not detected, only statements valid on every version are run: {e}"
+                    ));
+                }
+            };
+        let server: MySqlVersion = MySqlVersion::parse(&version, &comment);
+        let _ = self.server.set(server);
+        print_message(&format!("Server: {BLUE}{server}{RESET}"));
+        if server.driver() != self.config.driver {
+            return Some(format!(
+                "Driver {} is configured but the server is {server}",
+                self.config.driver
+            ));
+        }
+        None
+    }
+
+    /// Detect whether the server is a replica, from `read_only` and the replication status
+    /// Returns a warning when it is, the steps being skipped unless `allow_on_replica` is set
+    async fn detect_replica(&self, pool: &Pool<MySql>) -> Option<String> {
+        let read_only: i64 = sqlx::query_scalar("SELECT CAST(@@read_only AS SIGNED)")
+            .fetch_one(pool)
+            .await
+            .unwrap_or(0);
+        // The replication status needs REPLICATION CLIENT, a primary returns no row
+        let source: Option<String> = match pool.fetch_optional(self.server().replica_status_statement()).await {
+            Ok(Some(row)) => Some(
+                row.try_get::<String, _>("Source_Host")
+                    .or_else(|_| row.try_get::<String, _>("Master_Host"))
+                    .unwrap_or_default(),
+            ),
+            _ => None,
+        };
+        let replica: Option<String> = match source {
+            Some(host) => Some(format!("replicating from {host}")),
+            None if read_only != 0 => Some(String::from("read_only is ON")),
+            None => None,
+        };
+        let _ = self.replica.set(replica.clone());
+
+        let replica: String = replica?;
+        if self.config.allow_on_replica {
+            return Some(format!("The server is a replica ({replica}), steps are run because allow_on_replica is set"));
+        }
+        Some(format!("The server is a replica ({replica}), every step is skipped"))
+    }
+
+    /// Why no step may run on this server, None if it is not a replica or `allow_on_replica` is set
+    fn replica_skip_reason(&self) -> Option<String> {
+        if self.config.allow_on_replica {
+            return None;
+        }
+        let replica: &String = self.replica.get()?.as_ref()?;
+        Some(format!("the server is a replica ({replica})"))
+    }
+
+    /// Operations supported by this cleaner, in execution order
+    const OPERATIONS: [Operation; 11] = [
+        Operation::TemporaryObjects,
+        Operation::KillConnections,
+        Operation::Repair,
+        Operation::Optimize,
+        Operation::Reindex,
+        Operation::Analyze,
+        Operation::Flush,
+        Operation::FlushPrivileges,
+        Operation::PurgeLogs,
+        Operation::ResetStatistics,
+        Operation::ResetQueryStatistics,
+    ];
+
+    /// Retry the tables skipped because they were locked, once every step is done
+    async fn retry_locked(&self, pool: &Pool<MySql>, report: &mut CleanReport, first_step: usize) {
+        for (operation, statements) in report.take_locked(first_step) {
+            print_message(&format!("Retrying {operation} on {} locked tables...", statements.len()));
+            let mut step: StepReport = StepReport::new(operation, None);
+            step.detail = Some(String::from("retry of the locked tables"));
+            self.execute_on_tables(pool, &mut step, statements).await;
+            report.add_step(step.finish(Ok(())));
+        }
+    }
+
+    /// Why a step must not run in online-only mode, None if it may run
+    #[inline]
+    const fn online_skip_reason(&self, operation: Operation) -> Option<&'static str> {
+        match operation {
+            Operation::Repair if self.config.online_only => {
+                Some("CHECK TABLE and REPAIR TABLE lock the table (online_only)")
+            }
+            _ => None,
+        }
+    }
+
+    /// Statement rebuilding an `InnoDB` table, refused by the server instead of locking it in online-only mode
+    #[inline]
+    fn rebuild_statement(&self, table: &TableStatus) -> String {
+        if self.config.online_only {
+            format!("ALTER TABLE {} ENGINE=InnoDB, ALGORITHM=INPLACE, LOCK=NONE", table.table)
+        } else {
+            format!("ALTER TABLE {} ENGINE=InnoDB", table.table)
+        }
+    }
+
+    /// Execute a single cleaning step
+    /// `rebuilt` holds the tables already rewritten by a previous step of the run
+    async fn run_step(
+        &self,
+        pool: &Pool<MySql>,
+        operation: Operation,
+        tables: &[TableStatus],
+        rebuilt: &mut HashSet<String>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        match operation {
+            Operation::TemporaryObjects => {
+                print_message("Cleaning temporary tables...");
+                self.clean_temporary_objects(pool, step).await
+            }
+            Operation::KillConnections => {
+                print_message("Killing idle connections...");
+                self.kill_sleeping_connections(pool, step).await
+            }
+            Operation::Optimize => {
+                print_message("Optimizing fragmented InnoDB and MyISAM tables (defrag + analyze)...");
+                self.optimize_all_tables(pool, tables, rebuilt, step).await
+            }
+            Operation::Repair => {
+                print_message("Checking and repairing MyISAM, ARCHIVE and CSV tables if needed...");
+                self.check_and_repair_tables(pool, tables, rebuilt, step).await
+            }
+            Operation::Reindex => {
+                print_message("Rebuilding indexes for InnoDB tables...");
+                self.reindex_all_tables(pool, tables, rebuilt, step).await
+            }
+            Operation::Analyze => {
+                print_message("Updating table statistics...");
+                self.analyse_all_tables(pool, tables, rebuilt, step).await
+            }
+            Operation::Flush => {
+                print_message("Flushing caches and logs...");
+                self.flush_caches(pool, step).await
+            }
+            Operation::FlushPrivileges => {
+                print_message("Reloading privileges...");
+                self.flush_privileges(pool, step).await
+            }
+            Operation::PurgeLogs => {
+                print_message("Purging old binary and slow query logs...");
+                self.purge_logs(pool, step).await
+            }
+            Operation::ResetStatistics => {
+                print_message("Resetting statistics...");
+                self.reset_statistics(pool, step).await
+            }
+            Operation::ResetQueryStatistics => {
+                print_message("Resetting statement digests...");
+                self.reset_query_statistics(pool, step).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Execute a statement, or only print it in dry-run mode
+    #[inline]
+    async fn execute(&self, pool: &Pool<MySql>, sql: &str) -> Result<(), sqlx::Error> {
+        if self.config.dry_run {
+            print_message(&format!("{YELLOW}[DRY RUN]{RESET} {sql}"));
+            return Ok(());
+        }
+        Self::execute_until(pool, sql, self.cancel_at()).await
+    }
+
+    /// Moment at which running statements are cancelled, if configured
+    #[inline]
+    fn cancel_at(&self) -> Option<Instant> {
+        self.deadline.instant().filter(|_| self.config.cancel_on_deadline)
+    }
+
+    /// Execute a statement, cancelling it with `KILL QUERY` if it is still running at `cancel_at`
+    async fn execute_until(
+        pool: &Pool<MySql>,
+        sql: &str,
+        cancel_at: Option<Instant>,
+    ) -> Result<(), sqlx::Error> {
+        Self::fetch_until(pool, sql, cancel_at).await.map(|_| ())
+    }
+
+    /// Execute a statement returning rows, cancelling it with `KILL QUERY` if it is still running at `cancel_at`
+    async fn fetch_until(
+        pool: &Pool<MySql>,
+        sql: &str,
+        cancel_at: Option<Instant>,
+    ) -> Result<Vec<MySqlRow>, sqlx::Error> {
+        let Some(cancel_at) = cancel_at else {
+            return pool.fetch_all(sql).await;
+        };
+
+        let mut conn: PoolConnection<MySql> = pool.acquire().await?;
+        let connection_id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+            .fetch_one(&mut *conn)
+            .await?;
+        let execution = conn.fetch_all(sql);
+        tokio::pin!(execution);
+
+        tokio::select! {
+            result = &mut execution => result,
+            () = tokio::time::sleep_until(cancel_at.into()) => {
+                log_and_print(&format!("Deadline reached, cancelling: {sql}"), &LogType::Warning);
+                pool.execute(format!("KILL QUERY {connection_id}").as_str()).await?;
+                execution.await
+            }
+        }
+    }
+
+    /// Execute a statement on a table and record its duration in the step report
+    async fn execute_on_table(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+        table_name: &str,
+        sql: &str,
+    ) {
+        if self.deadline.is_reached() {
+            step.skip_table(table_name, self.deadline.reason());
+            return;
+        }
+        let start: Instant = Instant::now();
+        let result = self.execute(pool, sql).await;
+        step.add_statement(table_name, sql, start.elapsed(), result);
+    }
+
+    /// Execute a statement on each table, with up to `table_concurrency` tables at the same time
+    /// Statements are only printed one by one in dry-run mode
+    async fn execute_on_tables(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+        statements: Vec<(String, String)>,
+    ) {
+        if self.config.dry_run || self.config.table_concurrency <= 1 {
+            for (table_name, sql) in &statements {
+                self.execute_on_table(pool, step, table_name, sql).await;
+            }
+            return;
+        }
+
+        let cancel_at: Option<Instant> = self.cancel_at();
+        let not_started: Vec<(String, String)> = execute_concurrently(
+            self.config.table_concurrency,
+            statements,
+            self.deadline,
+            |sql| {
+                let pool: Pool<MySql> = pool.clone();
+                async move { Self::execute_until(&pool, &sql, cancel_at).await }
+            },
+            |table_name, sql, duration, result| step.add_statement(table_name, sql, duration, result),
+        )
+        .await;
+
+        for (table_name, _) in not_started {
+            step.skip_table(&table_name, self.deadline.reason());
+        }
+    }
+
+    /// Clean temporary tables
+    #[inline]
+    async fn clean_temporary_objects(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
         // Drop temporary tables
         const DROP_TEMP: &str = "DROP TEMPORARY TABLE IF EXISTS temp_tables";
-        if let Err(e) = pool.execute(DROP_TEMP).await {
-            log_and_print(
-                &format!("No temporary tables to drop: {e}"),
+        if let Err(e) = self.execute(pool, DROP_TEMP).await {
+            step.warn_and_print(
+                format!("No temporary tables to drop: {e}"),
                 &LogType::Info,
             );
         }
+        Ok(())
+    }
 
-        // Kill sleeping connections older than 1 hour
-        const KILL_QUERY: &str = r#"
-            SELECT CONCAT('KILL ', id, ';') AS kill_cmd
-            FROM information_schema.processlist
-            WHERE command = 'Sleep' AND time > 3600
-        "#;
-
-        match sqlx::query(KILL_QUERY).fetch_all(pool).await {
-            Ok(rows) => {
-                for row in rows {
-                    let kill_cmd: String = row.get("kill_cmd");
-                    if let Err(e) = pool.execute(kill_cmd.as_str()).await {
-                        log_and_print(
-                            &format!("Error killing connection: {e}"),
-                            &LogType::Warning,
-                        );
-                    }
-                }
+    /// Kill the connections sleeping for longer than `min_idle` and selected by the patterns (opt-in)
+    #[inline]
+    async fn kill_sleeping_connections(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let settings: &KillConnectionSettings = &self.config.kill_connections;
+        let connections: Vec<IdleConnection> = sqlx::query(IdleConnection::MYSQL_SQL)
+            .bind(IdleConnection::min_idle(settings).as_secs())
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(IdleConnection::from_mysql_row)
+            .collect::<Result<_, _>>()?;
+
+        for connection in &connections {
+            if let Some(reason) = connection.keep_reason(settings, &self.config.username) {
+                print_message(&format!("Keeping {}: {reason}", connection.describe()));
+                continue;
             }
-            Err(e) => {
-                log_and_print(
-                    &format!("Error fetching sleeping connections: {e}"),
-                    &LogType::Info,
-                );
+            if settings.report_only {
+                step.dry_run_remove("kill", connection.describe());
+                continue;
+            }
+            match self.execute(pool, &format!("KILL {};", connection.id)).await {
+                Ok(()) if self.config.dry_run => step.dry_run_remove("kill", connection.describe()),
+                Ok(()) => step.remove(connection.describe()),
+                Err(e) => step.warn_and_print(
+                    format!("Error killing connection {}: {e}", connection.id),
+                    &LogType::Warning,
+                ),
             }
         }
-
         Ok(())
     }
 
-    /// OPTIMIZE TABLE - combines defragmentation, analyze, and repair
+    /// OPTIMIZE TABLE - combines defragmentation and analyze
+    /// Only the `InnoDB` and `MyISAM` tables with enough free space, not yet rebuilt in this run, are optimized
+    /// In online-only mode, the `InnoDB` tables are rebuilt in place and the `MyISAM` tables, which would be locked, are skipped
     #[inline]
-    async fn optimize_all_tables(&self, pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
-        let all_tables: Vec<MySqlRow> = self.get_tables_from_schema(pool).await?;
-
-        for row in &all_tables {
-            let table_name: String = row.get("all_tables");
-            let optimize_sql = format!("OPTIMIZE TABLE {table_name}");
-
-            if let Err(e) = pool.execute(optimize_sql.as_str()).await {
-                log_and_print(
-                    &format!("Error optimizing table {table_name}: {e}"),
-                    &LogType::Warning,
-                );
+    async fn optimize_all_tables(
+        &self,
+        pool: &Pool<MySql>,
+        tables: &[TableStatus],
+        rebuilt: &mut HashSet<String>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut statements: Vec<(String, String)> = Vec::with_capacity(tables.len());
+        for table in tables {
+            let already_rebuilt: bool = rebuilt.contains(&table.table);
+            if let Some(reason) = table.optimize_skip_reason(&self.config.fragmentation, already_rebuilt) {
+                step.skip_table(&table.table, reason);
+                continue;
+            }
+            if self.config.online_only && table.engine == TableEngine::MyIsam {
+                step.skip_table(&table.table, "OPTIMIZE TABLE locks MyISAM tables (online_only)");
+                continue;
             }
+            print_message(&format!(
+                "Table {} ({}): ~{BLUE}{}{RESET} bytes reclaimable ({:.0}% free) -> OPTIMIZE",
+                table.table,
+                table.engine,
+                table.data_free.to_formatted_string(&Locale::en),
+                table.free_ratio() * 100.0
+            ));
+            let sql: String = if self.config.online_only {
+                self.rebuild_statement(table)
+            } else {
+                format!("OPTIMIZE TABLE {}", table.table)
+            };
+            statements.push((table.table.clone(), sql));
         }
 
+        self.execute_on_tables(pool, step, statements).await;
+        Self::mark_rebuilt(step, rebuilt);
+
+        for table in tables {
+            step.set_reclaimable_bytes(&table.table, table.data_free);
+        }
         Ok(())
     }
 
+    /// Remember the tables successfully rewritten by a step
+    fn mark_rebuilt(step: &StepReport, rebuilt: &mut HashSet<String>) {
+        rebuilt.extend(
+            step.tables
+                .iter()
+                .filter(|table| table.status == StepStatus::Success)
+                .map(|table| table.table.clone()),
+        );
+    }
+
     /// Flush caches and buffers (one-shot operation)
     #[inline]
-    async fn flush_caches(pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
-        const FLUSH_COMMANDS: [&str; 8] = [
-            "FLUSH TABLES;",          // Close all tables
-            "FLUSH HOSTS;",           // Reset host cache
-            "FLUSH STATUS;",          // Reset status variables
-            "FLUSH USER_RESOURCES;",  // Reset per-user resource limits
-            "FLUSH QUERY CACHE;",     // Clear the query cache (if enabled)
-            "RESET QUERY CACHE;",     // Reset query cache memory
-            "FLUSH PRIVILEGES;",      // Reload privilege tables
-            "FLUSH LOGS;",            // Flush all logs
-        ];
-
-        for cmd in &FLUSH_COMMANDS {
-            if let Err(e) = pool.execute(*cmd).await {
-                log_and_print(
-                    &format!("Error executing {cmd}: {e}"),
+    async fn flush_caches(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        // The query cache and FLUSH HOSTS do not exist on every version
+        for cmd in &self.server().flush_statements() {
+            // FLUSH TABLES waits for the running statements and blocks the new ones meanwhile
+            if self.config.online_only && cmd.starts_with("FLUSH TABLES") {
+                step.warn_and_print(format!("{cmd} skipped (online_only)"), &LogType::Info);
+                continue;
+            }
+            if let Err(e) = self.execute(pool, cmd).await {
+                step.warn_and_print(
+                    format!("Error executing {cmd}: {e}"),
                     &LogType::Warning,
                 );
             }
@@ -160,32 +577,60 @@ impl MySQLCleaner {
         Ok(())
     }
 
-    /// Purge old logs (binary logs and slow query logs)
+    /// Reload the privileges from the grant tables (opt-in)
     #[inline]
-    async fn purge_logs(pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
-        // Purge binary logs older than 7 days
-        const PURGE_BINARY: &str = "PURGE BINARY LOGS BEFORE DATE_SUB(NOW(), INTERVAL 7 DAY)";
-        if let Err(e) = pool.execute(PURGE_BINARY).await {
-            log_and_print(
-                &format!("Error purging binary logs: {e}"),
-                &LogType::Info,
+    async fn flush_privileges(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        const FLUSH_PRIVILEGES: &str = "FLUSH PRIVILEGES;";
+        if let Err(e) = self.execute(pool, FLUSH_PRIVILEGES).await {
+            step.warn_and_print(
+                format!("Error executing {FLUSH_PRIVILEGES}: {e}"),
+                &LogType::Warning,
             );
         }
+        Ok(())
+    }
+
+    /// Purge old logs (binary logs and slow query logs)
+    #[inline]
+    async fn purge_logs(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        // Purge the binary logs older than the retention, unless a replica may still need them
+        match self.binlog_keep_reason(pool).await {
+            Some(reason) => step.warn_and_print(
+                format!("Binary logs not purged: {reason}"),
+                &LogType::Warning,
+            ),
+            None => {
+                if let Err(e) = self.execute(pool, &purge_statement(&self.config.binlog)).await {
+                    step.warn_and_print(
+                        format!("Error purging binary logs: {e}"),
+                        &LogType::Info,
+                    );
+                }
+            }
+        }
 
         // Truncate slow query log table if it exists
         const TRUNCATE_SLOW_LOG: &str = "TRUNCATE TABLE mysql.slow_log";
-        if let Err(e) = pool.execute(TRUNCATE_SLOW_LOG).await {
-            log_and_print(
-                &format!("Slow query log table not available: {e}"),
+        if let Err(e) = self.execute(pool, TRUNCATE_SLOW_LOG).await {
+            step.warn_and_print(
+                format!("Slow query log table not available: {e}"),
                 &LogType::Info,
             );
         }
 
         // Truncate general log table if it exists
         const TRUNCATE_GENERAL_LOG: &str = "TRUNCATE TABLE mysql.general_log";
-        if let Err(e) = pool.execute(TRUNCATE_GENERAL_LOG).await {
-            log_and_print(
-                &format!("General log table not available: {e}"),
+        if let Err(e) = self.execute(pool, TRUNCATE_GENERAL_LOG).await {
+            step.warn_and_print(
+                format!("General log table not available: {e}"),
                 &LogType::Info,
             );
         }
@@ -193,20 +638,51 @@ impl MySQLCleaner {
         Ok(())
     }
 
-    /// Reset performance schema and statistics
+    /// Why the binary logs must be kept, None if no replica may still need them or `check_replicas` is off
+    /// The server only keeps the log a connected replica is reading, not the logs of a disconnected or lagging one
+    async fn binlog_keep_reason(&self, pool: &Pool<MySql>) -> Option<String> {
+        if !self.config.binlog.check_replicas {
+            return None;
+        }
+        let registered: usize = match pool.fetch_all(self.server().replicas_statement()).await {
+            Ok(rows) => rows.len(),
+            Err(e) => return Some(format!("the replicas could not be listed: {e}")),
+        };
+        let threads: Vec<DumpThread> = match sqlx::query(DumpThread::SQL).fetch_all(pool).await {
+            Ok(rows) => match rows.iter().map(DumpThread::from_row).collect() {
+                Ok(threads) => threads,
+                Err(e) => return Some(format!("the binlog dump threads could not be read: {e}")),
+            },
+            Err(e) => return Some(format!("the binlog dump threads could not be listed: {e}")),
+        };
+        ReplicaLinks { registered, threads }.keep_reason(&self.config.binlog)
+    }
+
+    /// Reset the status variables and the performance schema summaries (opt-in)
     #[inline]
-    async fn reset_statistics(pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
+    async fn reset_statistics(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        const FLUSH_STATUS: &str = "FLUSH STATUS;";
+        if let Err(e) = self.execute(pool, FLUSH_STATUS).await {
+            step.warn_and_print(
+                format!("Error executing {FLUSH_STATUS}: {e}"),
+                &LogType::Warning,
+            );
+        }
+
         // Reset performance schema statistics
-        const RESET_COMMANDS: [&str; 3] = [
-            "TRUNCATE TABLE performance_schema.events_statements_summary_by_digest",
+        const RESET_COMMANDS: [&str; 2] = [
             "TRUNCATE TABLE performance_schema.events_waits_summary_global_by_event_name",
             "TRUNCATE TABLE performance_schema.file_summary_by_instance",
         ];
 
         for cmd in &RESET_COMMANDS {
-            if let Err(e) = pool.execute(*cmd).await {
-                log_and_print(
-                    &format!("Performance schema table not available: {e}"),
+            if let Err(e) = self.execute(pool, cmd).await {
+                step.warn_and_print(
+                    format!("Performance schema table not available: {e}"),
                     &LogType::Info,
                 );
             }
@@ -215,63 +691,145 @@ impl MySQLCleaner {
         Ok(())
     }
 
-    /// Execute the ALTER TABLE command on all tables with the `InnoDB` engine
-    async fn reindex_all_tables(&self, pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
-        let all_tables: Vec<MySqlRow> =
-            sqlx::query(&Self::get_all_inno_db_tables_sql(&self.config.schema))
-                .fetch_all(pool)
-                .await?;
+    /// Reset the statement digests of the performance schema (opt-in)
+    #[inline]
+    async fn reset_query_statistics(
+        &self,
+        pool: &Pool<MySql>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        const RESET_DIGESTS: &str = "TRUNCATE TABLE performance_schema.events_statements_summary_by_digest";
+        if let Err(e) = self.execute(pool, RESET_DIGESTS).await {
+            step.warn_and_print(
+                format!("Performance schema table not available: {e}"),
+                &LogType::Info,
+            );
+        }
+        Ok(())
+    }
+
+    /// Rebuild the `InnoDB` tables with ALTER TABLE ... ENGINE=InnoDB
+    /// When the optimize step is enabled, it already rebuilt every table worth it
+    async fn reindex_all_tables(
+        &self,
+        pool: &Pool<MySql>,
+        tables: &[TableStatus],
+        rebuilt: &mut HashSet<String>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut statements: Vec<(String, String)> = Vec::with_capacity(tables.len());
+        for table in tables.iter().filter(|table| table.engine == TableEngine::InnoDb) {
+            match self.reindex_skip_reason(table, rebuilt) {
+                Some(reason) => step.skip_table(&table.table, reason),
+                None => statements.push((table.table.clone(), self.rebuild_statement(table))),
+            }
+        }
 
-        Self::loop_and_execute_query_my_sql(pool, &all_tables, "ALTER TABLE ").await;
+        self.execute_on_tables(pool, step, statements).await;
+        Self::mark_rebuilt(step, rebuilt);
 
         Ok(())
     }
 
+    /// Why an `InnoDB` table must not be rebuilt by the Reindex step, None if it should
+    /// When the Optimize step is enabled, it already rebuilt the fragmented tables it could
+    fn reindex_skip_reason(&self, table: &TableStatus, rebuilt: &HashSet<String>) -> Option<&'static str> {
+        if rebuilt.contains(&table.table) {
+            return Some("already rebuilt in this run");
+        }
+        if !self.config.is_operation_enabled(Operation::Optimize) {
+            return None;
+        }
+        // A fragmented table is left to the Optimize step, whose report tells why it failed, was locked or cut off
+        Some(
+            table
+                .optimize_skip_reason(&self.config.fragmentation, false)
+                .unwrap_or("not rebuilt by the optimize step, see its report"),
+        )
+    }
+
     /// Execute the REPAIR TABLE command only if necessary
+    /// Only the `MyISAM`, `ARCHIVE` and `CSV` tables support CHECK TABLE and REPAIR TABLE
     #[inline]
-    async fn check_and_repair_tables(&self, pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
+    async fn check_and_repair_tables(
+        &self,
+        pool: &Pool<MySql>,
+        tables: &[TableStatus],
+        rebuilt: &mut HashSet<String>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
         const CHECK_TABLE_SQL: &str = "CHECK TABLE ";
         const EXTENDED_SQL: &str = " EXTENDED;";
         const REPAIR_TABLE_SQL: &str = "REPAIR TABLE ";
-        const ALL_TABLES: &str = "all_tables";
         const MSG_TEXT: &str = "Msg_text";
 
-        // Fetch the list of all tables in the schema
-        let all_tables: Vec<MySqlRow> =
-            sqlx::query(&Self::get_all_repair_tables_sql(&self.config.schema))
-                .fetch_all(pool)
-                .await?;
-
-        for item in &all_tables {
-            let table_name: String = item.get(ALL_TABLES);
+        for table in tables.iter().filter(|table| table.engine.supports_repair()) {
+            let table_name: &str = &table.table;
+            if self.deadline.is_reached() {
+                step.skip_table(table_name, self.deadline.reason());
+                continue;
+            }
             let check_sql: String = format!("{CHECK_TABLE_SQL}{table_name}{EXTENDED_SQL}");
 
-            let result: MySqlRow = pool.fetch_one(&*check_sql).await?;
-            let msg_text: String = result.get(MSG_TEXT);
+            if self.config.dry_run {
+                print_message(&format!("{YELLOW}[DRY RUN]{RESET} {check_sql}"));
+                print_message(&format!(
+                    "{YELLOW}[DRY RUN]{RESET} {REPAIR_TABLE_SQL}{table_name}{EXTENDED_SQL} (only if the check fails)"
+                ));
+                continue;
+            }
 
-            if msg_text != "OK" {
-                println!("{YELLOW}Table {table_name} needs repair{RESET}");
+            // A table that cannot be checked is recorded and the next one checked
+            // A locked table is not retried, since the CHECK alone would not repair it
+            let start: Instant = Instant::now();
+            let rows: Vec<MySqlRow> = match Self::fetch_until(pool, &check_sql, self.cancel_at()).await {
+                Ok(rows) => rows,
+                Err(e) if is_lock_timeout(&e) => {
+                    log_and_print(&format!("Table {table_name} is locked, repair skipped: {e}"), &LogType::Warning);
+                    step.skip_table(table_name, LOCKED);
+                    continue;
+                }
+                Err(e) => {
+                    step.add_statement(table_name, &check_sql, start.elapsed(), Err(e));
+                    continue;
+                }
+            };
+            let needs_repair: bool = rows
+                .iter()
+                .any(|row| row.try_get::<String, _>(MSG_TEXT).map_or(true, |msg_text| msg_text != "OK"));
+
+            if needs_repair {
+                step.warn_and_print(format!("Table {table_name} needs repair"), &LogType::Warning);
 
                 let repair_sql: String = format!("{REPAIR_TABLE_SQL}{table_name}{EXTENDED_SQL}");
 
-                if let Err(e) = pool.execute(repair_sql.as_str()).await {
-                    log_and_print(
-                        &format!("Error repairing table {table_name}: {e}"),
-                        &LogType::Warning,
-                    );
-                }
+                self.execute_on_table(pool, step, table_name, &repair_sql).await;
             }
         }
+        Self::mark_rebuilt(step, rebuilt);
 
         Ok(())
     }
 
-    /// Execute the ANALYZE TABLE command on all tables
+    /// Execute the ANALYZE TABLE command on the tables whose statistics were not refreshed by a rebuild
     #[inline]
-    async fn analyse_all_tables(&self, pool: &Pool<MySql>) -> Result<(), Box<dyn Error>> {
-        let all_tables: Vec<MySqlRow> = self.get_tables_from_schema(pool).await?;
+    async fn analyse_all_tables(
+        &self,
+        pool: &Pool<MySql>,
+        tables: &[TableStatus],
+        rebuilt: &HashSet<String>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut statements: Vec<(String, String)> = Vec::with_capacity(tables.len());
+        for table in tables.iter().filter(|table| table.engine.supports_analyze()) {
+            if rebuilt.contains(&table.table) {
+                step.skip_table(&table.table, "statistics updated by the rebuild");
+                continue;
+            }
+            statements.push((table.table.clone(), format!("ANALYZE TABLE {}", table.table)));
+        }
 
-        Self::loop_and_execute_query_my_sql(pool, &all_tables, "ANALYZE TABLE ").await;
+        self.execute_on_tables(pool, step, statements).await;
 
         Ok(())
     }
@@ -287,84 +845,152 @@ impl MySQLCleaner {
         Ok(size.unwrap_or(0))
     }
 
-    /// Get all tables in the specified schema
+    /// Schemas bound to the catalog queries, empty for `*` which selects all non-system schemas
     #[inline]
-    async fn get_tables_from_schema(
-        &self,
-        pool: &Pool<MySql>,
-    ) -> Result<Vec<MySqlRow>, Box<dyn Error>> {
-        let all_tables: Vec<MySqlRow> = sqlx::query(&Self::get_all_tables_sql(&self.config.schema))
-            .fetch_all(pool)
-            .await?;
-        Ok(all_tables)
+    fn catalog_schemas(&self) -> Vec<String> {
+        if self.config.schema == "*" {
+            return Vec::new();
+        }
+        split_schema(&self.config.schema)
     }
 
-    /// Get all tables in the specified schema
-    #[inline]
-    pub fn get_all_tables_sql(schema: &str) -> String {
-        if schema == "*" {
-            return String::from(
-                "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys');",
-            );
-        }
-        let mut query_all_tables: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE TABLE_SCHEMA IN (",
-        );
-        query_all_tables.push_str(merge_schema(schema).as_str());
-        query_all_tables.push_str(");");
-        query_all_tables
+    /// Check the privileges of the user and the capabilities of the server for the planned steps
+    async fn preflight(&self, pool: &Pool<MySql>, tables: &[TableStatus]) -> Result<Preflight, sqlx::Error> {
+        let grants: Vec<String> = sqlx::query_scalar("SHOW GRANTS").fetch_all(pool).await?;
+        let performance_schema: i64 = sqlx::query_scalar("SELECT CAST(@@performance_schema AS SIGNED)")
+            .fetch_one(pool)
+            .await
+            .unwrap_or(0);
+
+        let mut preflight: Preflight = Preflight::new(self.server().to_string());
+        self.check_steps(&mut preflight, &MySqlGrants::parse(&grants), tables, performance_schema != 0);
+        Ok(preflight)
     }
 
-    /// Get all tables that need to be reindexed (`InnoDB`)
-    #[inline]
-    pub fn get_all_inno_db_tables_sql(schema: &str) -> String {
-        if schema == "*" {
-            return String::from(
-                "SELECT CONCAT('`', TABLE_SCHEMA, '`.`', TABLE_NAME, '` ENGINE=InnoDB') AS all_tables FROM information_schema.TABLES WHERE ENGINE = 'InnoDB' AND TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys');",
-            );
+    /// Record which planned steps will work with the given grants and tables
+    fn check_steps(
+        &self,
+        preflight: &mut Preflight,
+        grants: &MySqlGrants,
+        tables: &[TableStatus],
+        performance_schema: bool,
+    ) {
+        for engine in [TableEngine::InnoDb, TableEngine::MyIsam, TableEngine::RepairOnly, TableEngine::Other] {
+            let count: usize = tables.iter().filter(|table| table.engine == engine).count();
+            if count > 0 {
+                preflight.capabilities.push(format!("{engine}: {count} tables"));
+            }
+        }
+        if performance_schema {
+            preflight.capabilities.push(String::from("performance_schema"));
         }
-        let mut query_all_tables: String = String::from(
-            "SELECT CONCAT('`', TABLE_SCHEMA, '`.`', TABLE_NAME, '` ENGINE=InnoDB') AS all_tables FROM information_schema.TABLES WHERE ENGINE = 'InnoDB' AND TABLE_SCHEMA IN (",
-        );
-        query_all_tables.push_str(merge_schema(schema).as_str());
-        query_all_tables.push_str(");");
-        query_all_tables
-    }
 
-    /// Get all tables that need to be repaired (`MyISAM`, `ARCHIVE`, `CSV`)
-    #[inline]
-    pub fn get_all_repair_tables_sql(schema: &str) -> String {
-        if schema == "*" {
-            return String::from(
-                "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE ENGINE IN ('MyISAM', 'ARCHIVE', 'CSV') AND TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys');",
-            );
+        // Schemas of the tables processed by a step, without the privileges it needs
+        let missing_schemas = |engines: &dyn Fn(TableEngine) -> bool, privileges: &[&str]| -> (Vec<String>, bool) {
+            let mut schemas: Vec<&str> = tables
+                .iter()
+                .filter(|table| engines(table.engine))
+                .map(|table| table.schema.as_str())
+                .collect();
+            schemas.sort_unstable();
+            schemas.dedup();
+            let missing: Vec<&str> = schemas
+                .iter()
+                .copied()
+                .filter(|schema| !privileges.iter().all(|privilege| grants.has(privilege, Some(schema))))
+                .collect();
+            if missing.is_empty() {
+                return (Vec::new(), false);
+            }
+            let issue: String = format!("{} missing on {}", privileges.join(", "), missing.join(", "));
+            (vec![issue], missing.len() == schemas.len())
+        };
+        let server: MySqlVersion = self.server();
+        let has_any = |privileges: &[&str]| {
+            privileges
+                .iter()
+                .any(|privilege| grants.has_global(&server.privilege(privilege)))
+        };
+
+        for operation in Self::OPERATIONS {
+            if !self.config.is_operation_enabled(operation) {
+                continue;
+            }
+            let (issues, blocking): (Vec<String>, bool) = match operation {
+                Operation::KillConnections if !has_any(&["SUPER", "CONNECTION_ADMIN"]) => (
+                    vec![String::from("only the connections of this user can be killed without SUPER or CONNECTION_ADMIN")],
+                    false,
+                ),
+                Operation::Repair => missing_schemas(&|engine: TableEngine| engine.supports_repair(), &["SELECT", "INSERT"]),
+                // Online-only mode rebuilds the tables with ALTER TABLE instead of OPTIMIZE TABLE
+                Operation::Optimize if self.config.online_only => {
+                    missing_schemas(&|engine: TableEngine| engine == TableEngine::InnoDb, &["ALTER"])
+                }
+                Operation::Optimize => missing_schemas(&|engine: TableEngine| engine.supports_optimize(), &["SELECT", "INSERT"]),
+                Operation::Reindex => missing_schemas(&|engine: TableEngine| engine == TableEngine::InnoDb, &["ALTER"]),
+                Operation::Analyze => missing_schemas(&|engine: TableEngine| engine.supports_analyze(), &["SELECT", "INSERT"]),
+                Operation::Flush | Operation::FlushPrivileges if !grants.has_global("RELOAD") => {
+                    (vec![String::from("FLUSH needs RELOAD")], true)
+                }
+                Operation::PurgeLogs => {
+                    let mut issues: Vec<String> = Vec::new();
+                    if !has_any(&["SUPER", "BINLOG_ADMIN"]) {
+                        issues.push(String::from("PURGE BINARY LOGS needs SUPER or BINLOG_ADMIN"));
+                    }
+                    if !grants.has("DROP", Some("mysql")) {
+                        issues.push(String::from("truncating the log tables needs DROP on mysql"));
+                    }
+                    if self.config.binlog.check_replicas
+                        && !(grants.has_global("PROCESS")
+                            && (grants.has_global("REPLICATION SLAVE") || grants.has_global("REPLICATION REPLICA")))
+                    {
+                        issues.push(String::from(
+                            "checking the replicas needs PROCESS and REPLICATION SLAVE, binary logs are kept",
+                        ));
+                    }
+                    (issues, false)
+                }
+                Operation::ResetStatistics => {
+                    let mut issues: Vec<String> = Vec::new();
+                    let reload: bool = grants.has_global("RELOAD");
+                    let truncate: bool = performance_schema && grants.has("DROP", Some("performance_schema"));
+                    if !performance_schema {
+                        issues.push(String::from("performance_schema is disabled"));
+                    } else if !truncate {
+                        issues.push(String::from("truncating performance_schema tables needs DROP"));
+                    }
+                    if !reload {
+                        issues.push(String::from("FLUSH STATUS needs RELOAD"));
+                    }
+                    (issues, !reload && !truncate)
+                }
+                Operation::ResetQueryStatistics if !performance_schema => {
+                    (vec![String::from("performance_schema is disabled")], true)
+                }
+                Operation::ResetQueryStatistics if !grants.has("DROP", Some("performance_schema")) => {
+                    (vec![String::from("truncating performance_schema tables needs DROP")], true)
+                }
+                _ => (Vec::new(), false),
+            };
+            preflight.check(operation, issues, blocking);
         }
-        let mut query_all_tables: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE ENGINE IN ('MyISAM', 'ARCHIVE', 'CSV') AND TABLE_SCHEMA IN (",
-        );
-        query_all_tables.push_str(merge_schema(schema).as_str());
-        query_all_tables.push_str(");");
-        query_all_tables
     }
 
-    /// Loop through all tables and execute the specified command
+    /// Get the engine and free space of all base tables in the specified schema
     #[inline]
-    pub async fn loop_and_execute_query_my_sql(
-        pool: &Pool<MySql>,
-        all_tables: &[MySqlRow],
-        command: &str,
-    ) {
-        const QUERY_INDEX: &str = "all_tables";
-        for row in all_tables {
-            let table_name: String = row.get(QUERY_INDEX);
-            let sql_to_execute: String = format!("{command}{table_name}");
-            if let Err(e) = pool.execute(sql_to_execute.as_str()).await {
-                log_and_print(
-                    &format!("Error for table {table_name}: {e}"),
-                    &LogType::Error,
-                );
-            }
+    async fn get_table_status(&self, pool: &Pool<MySql>) -> Result<Vec<TableStatus>, Box<dyn Error>> {
+        let schemas: Vec<String> = self.catalog_schemas();
+        let sql: String = TableStatus::table_status_sql(&schemas);
+        let mut query = sqlx::query(&sql);
+        for schema in &schemas {
+            query = query.bind(schema);
         }
+        let all_tables: Vec<MySqlRow> = query.fetch_all(pool).await?;
+        let tables: Vec<TableStatus> = all_tables
+            .iter()
+            .map(TableStatus::from_row)
+            .collect::<Result<_, _>>()?;
+        Ok(tables)
     }
 }
 
@@ -386,81 +1012,117 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_all_inno_db_tables_sql() {
-        let schema: String = String::from("test");
-        let tested_sql: String = MySQLCleaner::get_all_inno_db_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`', TABLE_SCHEMA, '`.`', TABLE_NAME, '` ENGINE=InnoDB') AS all_tables FROM information_schema.TABLES WHERE ENGINE = 'InnoDB' AND TABLE_SCHEMA IN ('test');",
-        );
-        assert_eq!(tested_sql, true_sql);
-
-        let schema: String = String::from("test,test1");
-        let tested_sql: String = MySQLCleaner::get_all_inno_db_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`', TABLE_SCHEMA, '`.`', TABLE_NAME, '` ENGINE=InnoDB') AS all_tables FROM information_schema.TABLES WHERE ENGINE = 'InnoDB' AND TABLE_SCHEMA IN ('test','test1');",
-        );
-        assert_eq!(tested_sql, true_sql);
-
-        let schema: String = String::from("*");
-        let tested_sql: String = MySQLCleaner::get_all_inno_db_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`', TABLE_SCHEMA, '`.`', TABLE_NAME, '` ENGINE=InnoDB') AS all_tables FROM information_schema.TABLES WHERE ENGINE = 'InnoDB' AND TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys');",
-        );
-        assert_eq!(tested_sql, true_sql);
+    async fn test_check_steps() {
+        use crate::cleaner::preflight::Availability;
+        let table = |schema: &str, engine: TableEngine| TableStatus {
+            table: format!("`{schema}`.`t`"),
+            schema: schema.to_string(),
+            engine,
+            data_length: 0,
+            data_free: 0,
+        };
+        let tables: Vec<TableStatus> = vec![
+            table("app", TableEngine::InnoDb),
+            table("billing", TableEngine::InnoDb),
+            table("logs", TableEngine::RepairOnly),
+        ];
+        let grants = MySqlGrants::parse(&[
+            "GRANT RELOAD ON *.* TO `cleaner`@`%`",
+            "GRANT ALL PRIVILEGES ON `app`.* TO `cleaner`@`%`",
+        ]);
+        let mut config: Config = get_test_config(ConnectionEngine::Mysql, "3306");
+        config.operations = Some(Operation::ALL.to_vec());
+        let cleaner: MySQLCleaner = MySQLCleaner::new(config);
+        cleaner
+            .server
+            .set(MySqlVersion::parse("8.0.36", "MySQL Community Server - GPL"))
+            .unwrap();
+        let mut preflight: Preflight = Preflight::new(cleaner.server().to_string());
+        cleaner.check_steps(&mut preflight, &grants, &tables, false);
+
+        let availability = |operation: Operation| {
+            preflight.checks.iter().find(|check| check.operation == operation).map(|check| check.availability)
+        };
+        assert_eq!(availability(Operation::Optimize), Some(Availability::Partial));
+        assert_eq!(availability(Operation::Repair), Some(Availability::Unavailable));
+        assert_eq!(availability(Operation::Flush), Some(Availability::Ready));
+        assert_eq!(availability(Operation::PurgeLogs), Some(Availability::Partial));
+        // FLUSH STATUS still works without performance_schema
+        assert_eq!(availability(Operation::ResetStatistics), Some(Availability::Partial));
+        assert_eq!(availability(Operation::ResetQueryStatistics), Some(Availability::Unavailable));
+        assert_eq!(availability(Operation::KillConnections), Some(Availability::Partial));
+        assert_eq!(preflight.capabilities, vec!["InnoDB: 2 tables", "ARCHIVE/CSV: 1 tables"]);
     }
 
     #[tokio::test]
-    async fn test_get_all_repair_tables_sql() {
-        let schema: String = String::from("test");
-        let tested_sql: String = MySQLCleaner::get_all_repair_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE ENGINE IN ('MyISAM', 'ARCHIVE', 'CSV') AND TABLE_SCHEMA IN ('test');",
-        );
-        assert_eq!(tested_sql, true_sql);
-
-        let schema: String = String::from("test,test1");
-        let tested_sql: String = MySQLCleaner::get_all_repair_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE ENGINE IN ('MyISAM', 'ARCHIVE', 'CSV') AND TABLE_SCHEMA IN ('test','test1');",
-        );
-        assert_eq!(tested_sql, true_sql);
-
-        let schema: String = String::from("*");
-        let tested_sql: String = MySQLCleaner::get_all_repair_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE ENGINE IN ('MyISAM', 'ARCHIVE', 'CSV') AND TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys');",
+    async fn test_online_only() {
+        let table = TableStatus {
+            table: String::from("`app`.`orders`"),
+            schema: String::from("app"),
+            engine: TableEngine::InnoDb,
+            data_length: 0,
+            data_free: 0,
+        };
+        let mut config: Config = get_test_config(ConnectionEngine::Mysql, "3306");
+        assert_eq!(MySQLCleaner::new(config.clone()).rebuild_statement(&table), "ALTER TABLE `app`.`orders` ENGINE=InnoDB");
+        assert_eq!(MySQLCleaner::new(config.clone()).online_skip_reason(Operation::Repair), None);
+
+        config.online_only = true;
+        let cleaner: MySQLCleaner = MySQLCleaner::new(config);
+        assert_eq!(
+            cleaner.rebuild_statement(&table),
+            "ALTER TABLE `app`.`orders` ENGINE=InnoDB, ALGORITHM=INPLACE, LOCK=NONE"
         );
-        assert_eq!(tested_sql, true_sql);
+        assert!(cleaner.online_skip_reason(Operation::Repair).is_some());
+        assert_eq!(cleaner.online_skip_reason(Operation::Analyze), None);
     }
 
     #[tokio::test]
-    async fn test_get_all_tables_sql() {
-        let schema: String = String::from("test");
-        let tested_sql: String = MySQLCleaner::get_all_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE TABLE_SCHEMA IN ('test');",
+    async fn test_reindex_skip_reason() {
+        let table = |name: &str, data_free: i64| TableStatus {
+            table: name.to_string(),
+            schema: String::from("app"),
+            engine: TableEngine::InnoDb,
+            data_length: 1000,
+            data_free,
+        };
+        let (compact, fragmented) = (table("`app`.`compact`", 0), table("`app`.`fragmented`", 500));
+        let rebuilt: HashSet<String> = HashSet::from([String::from("`app`.`rebuilt`")]);
+        let mut config: Config = get_test_config(ConnectionEngine::Mysql, "3306");
+        let cleaner: MySQLCleaner = MySQLCleaner::new(config.clone());
+        assert_eq!(cleaner.reindex_skip_reason(&table("`app`.`rebuilt`", 500), &rebuilt), Some("already rebuilt in this run"));
+        assert_eq!(cleaner.reindex_skip_reason(&compact, &rebuilt), Some("below fragmentation thresholds"));
+        assert_eq!(
+            cleaner.reindex_skip_reason(&fragmented, &rebuilt),
+            Some("not rebuilt by the optimize step, see its report")
         );
-        assert_eq!(tested_sql, true_sql);
 
-        let schema: String = String::from("test,test1");
-        let tested_sql: String = MySQLCleaner::get_all_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE TABLE_SCHEMA IN ('test','test1');",
-        );
-        assert_eq!(tested_sql, true_sql);
+        config.skip_operations = vec![Operation::Optimize];
+        let cleaner: MySQLCleaner = MySQLCleaner::new(config);
+        assert_eq!(cleaner.reindex_skip_reason(&compact, &rebuilt), None);
+        assert_eq!(cleaner.reindex_skip_reason(&fragmented, &rebuilt), None);
+    }
 
-        let schema: String = String::from("test,test1,test2");
-        let tested_sql: String = MySQLCleaner::get_all_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE TABLE_SCHEMA IN ('test','test1','test2');",
-        );
-        assert_eq!(tested_sql, true_sql);
+    #[tokio::test]
+    async fn test_replica_skip_reason() {
+        let mut config: Config = get_test_config(ConnectionEngine::Mysql, "3306");
+        let cleaner: MySQLCleaner = MySQLCleaner::new(config.clone());
+        assert_eq!(cleaner.replica_skip_reason(), None);
+        cleaner.replica.set(Some(String::from("read_only is ON"))).unwrap();
+        assert_eq!(cleaner.replica_skip_reason().as_deref(), Some("the server is a replica (read_only is ON)"));
+
+        config.allow_on_replica = true;
+        let cleaner: MySQLCleaner = MySQLCleaner::new(config);
+        cleaner.replica.set(Some(String::from("read_only is ON"))).unwrap();
+        assert_eq!(cleaner.replica_skip_reason(), None);
+    }
 
-        let schema: String = String::from("*");
-        let tested_sql: String = MySQLCleaner::get_all_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT CONCAT('`',TABLE_SCHEMA,'`.`', TABLE_NAME, '`') AS all_tables FROM information_schema.TABLES WHERE TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys');",
-        );
-        assert_eq!(tested_sql, true_sql);
+    #[tokio::test]
+    async fn test_catalog_schemas() {
+        let mut config: Config = get_test_config(ConnectionEngine::Mysql, "3306");
+        config.schema = String::from("app, it's");
+        assert_eq!(MySQLCleaner::new(config.clone()).catalog_schemas(), vec!["app", "it's"]);
+        config.schema = String::from("*");
+        assert!(MySQLCleaner::new(config).catalog_schemas().is_empty());
     }
 }
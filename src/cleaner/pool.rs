@@ -0,0 +1,137 @@
+use crate::config::{Config, ConnectionEngine};
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{Executor, MySql, Pool, Postgres};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Time waited for a free connection when `acquire_timeout` is not set
+const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connections kept free for the `KILL QUERY` or `pg_cancel_backend` cancelling running statements
+const CANCEL_CONNECTIONS: u32 = 1;
+
+/// Maximum number of connections of the pools opened for a database
+#[must_use]
+pub fn max_connections(config: &Config) -> u32 {
+    config.pool.max_connections.unwrap_or_else(|| {
+        // Keep a connection available for every table processed concurrently, and one to cancel them
+        u32::try_from(config.table_concurrency)
+            .unwrap_or(u32::MAX)
+            .saturating_add(CANCEL_CONNECTIONS)
+    })
+}
+
+/// Session settings of the `PostgreSQL` connections, sent as startup options
+/// The session is read-only in dry-run mode
+#[must_use]
+pub fn postgres_session_options(config: &Config) -> Vec<(&'static str, String)> {
+    let mut options: Vec<(&'static str, String)> = Vec::new();
+    if let Some(timeout) = config.pool.statement_timeout {
+        options.push(("statement_timeout", timeout.as_millis().to_string()));
+    }
+    if let Some(timeout) = config.pool.lock_timeout {
+        options.push(("lock_timeout", timeout.as_millis().to_string()));
+    }
+    if config.dry_run {
+        options.push(("default_transaction_read_only", String::from("on")));
+    }
+    options
+}
+
+/// Statements run on every new `MySQL` connection
+/// The session is read-only in dry-run mode
+#[must_use]
+pub fn mysql_session_statements(config: &Config) -> Vec<String> {
+    let mut statements: Vec<String> = Vec::new();
+    // MariaDB applies its limit to every statement, in seconds
+    // MySQL only limits SELECT statements, so the setting is rejected by the configuration validation
+    if let Some(timeout) = config.pool.statement_timeout.filter(|_| config.driver == ConnectionEngine::MariaDB) {
+        statements.push(format!("SET SESSION max_statement_time = {}", timeout.as_secs_f64()));
+    }
+    if let Some(timeout) = config.pool.lock_timeout {
+        // Metadata locks, taken by OPTIMIZE and ALTER TABLE, are waited for in whole seconds
+        statements.push(format!("SET SESSION lock_wait_timeout = {}", timeout.as_secs().max(1)));
+    }
+    if config.dry_run {
+        statements.push(String::from("SET SESSION TRANSACTION READ ONLY"));
+    }
+    statements
+}
+
+/// Open a `PostgreSQL` pool with the pool settings of the database
+pub async fn connect_postgres(config: &Config, database_url: &str) -> Result<Pool<Postgres>, sqlx::Error> {
+    let options: PgConnectOptions = PgConnectOptions::from_str(database_url)?
+        .application_name(&config.pool.application_name)
+        .options(postgres_session_options(config));
+    PgPoolOptions::new()
+        .max_connections(max_connections(config))
+        .acquire_timeout(config.pool.acquire_timeout.unwrap_or(DEFAULT_ACQUIRE_TIMEOUT))
+        .connect_with(options)
+        .await
+}
+
+/// Open a `MySQL` pool with the pool settings of the database
+pub async fn connect_mysql(config: &Config, database_url: &str) -> Result<Pool<MySql>, sqlx::Error> {
+    let options: MySqlConnectOptions = MySqlConnectOptions::from_str(database_url)?;
+    let statements: Vec<String> = mysql_session_statements(config);
+    MySqlPoolOptions::new()
+        .max_connections(max_connections(config))
+        .acquire_timeout(config.pool.acquire_timeout.unwrap_or(DEFAULT_ACQUIRE_TIMEOUT))
+        .after_connect(move |conn, _| {
+            let statements: Vec<String> = statements.clone();
+            Box::pin(async move {
+                for statement in &statements {
+                    conn.execute(statement.as_str()).await?;
+                }
+                Ok(())
+            })
+        })
+        .connect_with(options)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::get_test_config;
+
+    #[tokio::test]
+    async fn test_max_connections() {
+        let mut config: Config = get_test_config(ConnectionEngine::Postgres, "5432");
+        assert_eq!(max_connections(&config), config.table_concurrency as u32 + 1);
+        config.table_concurrency = 16;
+        assert_eq!(max_connections(&config), 17);
+        config.pool.max_connections = Some(4);
+        assert_eq!(max_connections(&config), 4);
+    }
+
+    #[tokio::test]
+    async fn test_session_settings() {
+        let mut config: Config = get_test_config(ConnectionEngine::Postgres, "5432");
+        assert!(postgres_session_options(&config).is_empty());
+        config.pool.statement_timeout = Some(Duration::from_secs(90));
+        config.dry_run = true;
+        assert_eq!(
+            postgres_session_options(&config),
+            vec![
+                ("statement_timeout", String::from("90000")),
+                ("default_transaction_read_only", String::from("on")),
+            ]
+        );
+
+        config.driver = ConnectionEngine::MariaDB;
+        assert_eq!(
+            mysql_session_statements(&config),
+            vec!["SET SESSION max_statement_time = 90", "SET SESSION TRANSACTION READ ONLY"]
+        );
+        config.driver = ConnectionEngine::Mysql;
+        config.dry_run = false;
+        assert!(mysql_session_statements(&config).is_empty());
+
+        config.pool.statement_timeout = None;
+        config.pool.lock_timeout = Some(Duration::from_millis(500));
+        assert_eq!(mysql_session_statements(&config), vec!["SET SESSION lock_wait_timeout = 1"]);
+        assert_eq!(postgres_session_options(&config), vec![("lock_timeout", String::from("500"))]);
+    }
+}
@@ -1,60 +1,179 @@
+use crate::cleaner::bloat::{TableBloat, VacuumAction};
 use crate::cleaner::database_cleaner::DatabaseCleaner;
-use crate::colors::{BLUE, RESET};
-use crate::config::Config;
-use crate::helpers::{get_url_connection, log_report, merge_schema};
-use crate::logger::{log_and_print, LogType};
+use crate::cleaner::deadline::Deadline;
+use crate::cleaner::idle_connection::IdleConnection;
+use crate::cleaner::pool::connect_postgres;
+use crate::cleaner::preflight::Preflight;
+use crate::cleaner::replication_slot::ReplicationSlot;
+use crate::cleaner::report::{CleanReport, StepReport, StepStatus};
+use crate::cleaner::server_version::PgVersion;
+use crate::colors::{BLUE, RESET, YELLOW};
+use crate::config::{Config, KillConnectionSettings, Operation};
+use crate::helpers::{
+    execute_concurrently, get_url_connection, is_lock_timeout, log_report, warn_discarded_state,
+};
+use crate::logger::{log_and_print, print_message, LogType};
+use crate::quote::Dialect;
 use async_trait::async_trait;
 use num_format::{Locale, ToFormattedString};
+use sqlx::pool::PoolConnection;
 use sqlx::postgres::PgRow;
 use sqlx::{Pool, Postgres, Row};
+use std::collections::HashSet;
 use std::error::Error;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Schema-qualified `PostgreSQL` table
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct PgTable {
+    pub schema: String,
+    pub name: String,
+}
+
+impl PgTable {
+    /// Create a table from its schema and name
+    #[inline]
+    pub fn new(schema: &str, name: &str) -> Self {
+        Self {
+            schema: schema.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    /// Read a table from a row with `schemaname` and `tablename` columns
+    pub fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            schema: row.try_get("schemaname")?,
+            name: row.try_get("tablename")?,
+        })
+    }
+
+    /// Quoted name of the table, safe to splice into statements
+    #[inline]
+    #[must_use]
+    pub fn quoted(&self) -> String {
+        Dialect::Postgres.quote_qualified(&self.schema, &self.name)
+    }
+}
+
+impl std::fmt::Display for PgTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.schema, self.name)
+    }
+}
 
 #[non_exhaustive]
 pub struct PostgresCleaner {
     pub config: Config,
+    deadline: Deadline,
+    /// Version of the server, detected once connected
+    server: OnceLock<PgVersion>,
+    /// Whether the server is a standby in recovery, detected once connected
+    standby: OnceLock<bool>,
 }
 
 #[async_trait]
 impl DatabaseCleaner for PostgresCleaner {
-    async fn clean(&self) -> Result<(), Box<dyn Error>> {
-        println!("Cleaning PostgresCleaner database...");
+    async fn clean(&self) -> Result<CleanReport, Box<dyn Error>> {
+        print_message("Cleaning PostgresCleaner database...");
 
-        let database_url: String = if self.config.schema == "*" {
-            get_url_connection(&self.config, "")?
-        } else {
-            let first_schema: String = merge_schema(&self.config.schema);
-            let first_schema: &str = first_schema.split(',').next().unwrap();
-            let first_schema_clean = first_schema.replace('\'', "");
-            get_url_connection(&self.config, &first_schema_clean)?
-        };
+        let databases: Option<Vec<String>> = self.config.postgres_databases();
+        let main_database: String = databases.iter().flatten().next().cloned().unwrap_or_default();
+        let database_url: String = get_url_connection(&self.config, &main_database)?;
 
-        let pool_size: Pool<Postgres> = Pool::connect(&database_url).await?;
-        let start_bytes_size: i64 = self.get_size_of_database(&pool_size).await.unwrap_or(0);
-        println!(
+        let main_pool: Pool<Postgres> = connect_postgres(&self.config, &database_url).await?;
+        let mut report: CleanReport = CleanReport::new(&self.config);
+        if let Some(warning) = self.detect_server(&main_pool).await {
+            report.warn(warning);
+        }
+        if let Some(warning) = self.detect_standby(&main_pool).await {
+            report.warn(warning);
+        }
+        // The sizes are measured on the databases that get cleaned
+        let databases: Vec<String> = match databases {
+            Some(databases) => databases,
+            // The error is not Send, so it must not be held while the pool closes
+            None => match self.get_all_datnames(&main_pool).await.map_err(|e| e.to_string()) {
+                Ok(databases) => databases,
+                Err(e) => {
+                    main_pool.close().await;
+                    return Err(e.into());
+                }
+            },
+        };
+        report.start_bytes = Self::get_size_of_database(&main_pool, Some(&databases)).await.unwrap_or(0);
+        print_message(&format!(
             "Size of database at start: {BLUE}{}{RESET} bytes",
-            start_bytes_size.to_formatted_string(&Locale::en)
-        );
+            report.start_bytes.to_formatted_string(&Locale::en)
+        ));
 
-        let schema_name: Vec<String> = if self.config.schema == "*" {
-            self.get_all_datnames(&pool_size).await?
-        } else {
-            merge_schema(&self.config.schema)
-                .split(',')
-                .map(|s| s.replace('\'', "").trim().into())
-                .collect()
-        };
+        // Nothing is modified before the privileges are checked
+        match self.preflight(&main_pool).await {
+            Ok(preflight) => {
+                preflight.print();
+                report.set_preflight(preflight);
+            }
+            Err(e) => report.warn(format!("Preflight failed: {e}")),
+        }
+        warn_discarded_state(&self.config);
 
-        for schema in &schema_name {
-            println!("Cleaning schema: {schema}");
-            let database_url: String = get_url_connection(&self.config, schema)?;
-            let pool: Pool<Postgres> = Pool::connect(&database_url).await?;
-            self.run(&pool, schema).await?;
+        for database in &databases {
+            print_message(&format!("Cleaning database: {database}"));
+            // A pool is bound to a database, only the main one is reused
+            let result = if *database == main_database {
+                self.run(&main_pool, database, &mut report).await
+            } else {
+                match get_url_connection(&self.config, database).map_err(|e| e.to_string()) {
+                    Ok(database_url) => match connect_postgres(&self.config, &database_url).await {
+                        Ok(pool) => {
+                            let result = self.run(&pool, database, &mut report).await;
+                            pool.close().await;
+                            result
+                        }
+                        Err(e) => Err(e.to_string()),
+                    },
+                    Err(e) => Err(e),
+                }
+            };
+            if let Err(e) = result {
+                log_and_print(&format!("Error cleaning {database}: {e}"), &LogType::Error);
+                report.error(format!("{database}: {e}"));
+            }
         }
 
-        let end_bytes_size: i64 = self.get_size_of_database(&pool_size).await?;
+        report.end_bytes = Self::get_size_of_database(&main_pool, Some(&databases)).await.unwrap_or(0);
+        main_pool.close().await;
 
-        log_report(start_bytes_size, end_bytes_size);
+        log_report(report.start_bytes, report.end_bytes);
+
+        Ok(report.finish())
+    }
+
+    async fn inspect(&self) -> Result<(), Box<dyn Error>> {
+        let database_url: String = self.get_main_database_url()?;
+
+        let pool: Pool<Postgres> = connect_postgres(&self.config, &database_url).await?;
+        if let Some(warning) = self.detect_server(&pool).await {
+            log_and_print(&warning, &LogType::Warning);
+        }
+        if let Some(warning) = self.detect_standby(&pool).await {
+            log_and_print(&warning, &LogType::Warning);
+        }
+        let preflight: Preflight = self.preflight(&pool).await?;
+        let databases: Option<Vec<String>> = self.config.postgres_databases();
+        let size: i64 = Self::get_size_of_database(&pool, databases.as_deref()).await.unwrap_or(0);
+        let all_tables: Vec<PgTable> = Self::get_all_tables(&pool, self.config.postgres_schemas()).await?;
 
+        print_message(&format!(
+            "Size of database: {BLUE}{}{RESET} bytes",
+            size.to_formatted_string(&Locale::en)
+        ));
+        print_message(&format!("Tables to clean: {BLUE}{}{RESET}", all_tables.len()));
+        preflight.print();
+
+        pool.close().await;
         Ok(())
     }
 
@@ -65,115 +184,498 @@ impl DatabaseCleaner for PostgresCleaner {
 
 impl PostgresCleaner {
     /// Create a new `PostgresCleaner` instance with the given configuration
+    /// The deadline of the maintenance window and `max_duration` starts now
     #[inline]
     #[must_use]
-    pub const fn new(config: Config) -> Self {
-        Self { config }
+    pub fn new(config: Config) -> Self {
+        let deadline: Deadline = Deadline::from_config(&config);
+        Self {
+            config,
+            deadline,
+            server: OnceLock::new(),
+            standby: OnceLock::new(),
+        }
     }
 
-    /// Execute the cleaning process into a single function to avoid query repetition
-    async fn run(&self, pool: &Pool<Postgres>, schema_name: &str) -> Result<(), Box<dyn Error>> {
-        let all_tables: Vec<PgRow> =
-            match sqlx::query(&Self::get_all_postgres_tables_sql(schema_name))
-                .fetch_all(pool)
-                .await
-            {
-                Ok(rows) => rows,
-                Err(e) => {
-                    log_and_print(&format!("Error fetching tables: {e}"), &LogType::Error);
-                    return Err(Box::new(e));
-                }
-            };
+    /// Version of the server, or an unknown version before it is detected
+    #[inline]
+    fn server(&self) -> PgVersion {
+        self.server.get().copied().unwrap_or_else(PgVersion::unknown)
+    }
 
-        println!("Cleaning temporary objects...");
-        self.drop_temp_tables(pool).await?;
-        self.clean_prepared_transactions(pool).await?;
+    /// Detect the version of the server, returning a warning if it could not be read
+    async fn detect_server(&self, pool: &Pool<Postgres>) -> Option<String> {
+        let version: String = match sqlx::query_scalar("SELECT current_setting('server_version_num')")
+            .fetch_one(pool)
+            .await
+        {
+            Ok(version) => version,
+            Err(e) => {
+                return Some(format!(
+                    "Server version not detected, only statements valid on every version are run: {e}"
+                ));
+            }
+        };
+        let server: PgVersion = PgVersion::parse(&version);
+        let _ = self.server.set(server);
+        print_message(&format!("Server: {BLUE}{server}{RESET}"));
+        None
+    }
 
-        println!("Cleaning dead rows and updating statistics...");
-        self.vacuum_databases(pool, &all_tables).await?;
+    /// Execute a statement, or only print it in dry-run mode
+    #[inline]
+    async fn execute(&self, pool: &Pool<Postgres>, sql: &str) -> Result<(), sqlx::Error> {
+        if self.config.dry_run {
+            print_message(&format!("{YELLOW}[DRY RUN]{RESET} {sql}"));
+            return Ok(());
+        }
+        Self::execute_until(pool, sql, self.cancel_at()).await
+    }
 
-        println!("Reindexing all tables...");
-        self.reindex_all_tables(pool, &all_tables).await?;
+    /// Moment at which running statements are cancelled, if configured
+    #[inline]
+    fn cancel_at(&self) -> Option<Instant> {
+        self.deadline.instant().filter(|_| self.config.cancel_on_deadline)
+    }
 
-        println!("Optimizing table storage layout...");
-        self.cluster_tables(pool, &all_tables).await?;
+    /// Execute a statement, cancelling it with `pg_cancel_backend` if it is still running at `cancel_at`
+    async fn execute_until(
+        pool: &Pool<Postgres>,
+        sql: &str,
+        cancel_at: Option<Instant>,
+    ) -> Result<(), sqlx::Error> {
+        let Some(cancel_at) = cancel_at else {
+            sqlx::query(sql).execute(pool).await?;
+            return Ok(());
+        };
 
-        println!("Cleaning bloated tables and indexes...");
-        self.clean_bloat(pool).await?;
+        let mut conn: PoolConnection<Postgres> = pool.acquire().await?;
+        let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+            .fetch_one(&mut *conn)
+            .await?;
+        let execution = sqlx::query(sql).execute(&mut *conn);
+        tokio::pin!(execution);
 
-        println!("Truncating WAL and clearing old logs...");
-        self.clean_wal_and_logs(pool).await?;
+        tokio::select! {
+            result = &mut execution => {
+                result?;
+            }
+            () = tokio::time::sleep_until(cancel_at.into()) => {
+                log_and_print(&format!("Deadline reached, cancelling: {sql}"), &LogType::Warning);
+                sqlx::query("SELECT pg_cancel_backend($1)")
+                    .bind(pid)
+                    .execute(pool)
+                    .await?;
+                execution.await?;
+            }
+        }
+        Ok(())
+    }
 
-        println!("Updating global statistics...");
-        self.update_statistics(pool).await?;
+    /// Execute a statement on a table and record its duration in the step report
+    async fn execute_on_table(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+        table_name: &str,
+        sql: &str,
+    ) {
+        if self.deadline.is_reached() {
+            step.skip_table(table_name, self.deadline.reason());
+            return;
+        }
+        let start: Instant = Instant::now();
+        let result = self.execute(pool, sql).await;
+        step.add_statement(table_name, sql, start.elapsed(), result);
+    }
 
+    /// Execute a statement on each table, with up to `table_concurrency` tables at the same time
+    /// Statements are only printed one by one in dry-run mode
+    async fn execute_on_tables(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+        statements: Vec<(String, String)>,
+    ) {
+        if self.config.dry_run || self.config.table_concurrency <= 1 {
+            for (table_name, sql) in &statements {
+                self.execute_on_table(pool, step, table_name, sql).await;
+            }
+            return;
+        }
+
+        let cancel_at: Option<Instant> = self.cancel_at();
+        let not_started: Vec<(String, String)> = execute_concurrently(
+            self.config.table_concurrency,
+            statements,
+            self.deadline,
+            |sql| {
+                let pool: Pool<Postgres> = pool.clone();
+                async move { Self::execute_until(&pool, &sql, cancel_at).await }
+            },
+            |table_name, sql, duration, result| step.add_statement(table_name, sql, duration, result),
+        )
+        .await;
+
+        for (table_name, _) in not_started {
+            step.skip_table(&table_name, self.deadline.reason());
+        }
+    }
+
+    /// Get the url of the first configured database, or of the default database for `*`
+    fn get_main_database_url(&self) -> Result<String, Box<dyn Error>> {
+        let databases: Vec<String> = self.config.postgres_databases().unwrap_or_default();
+        get_url_connection(&self.config, databases.first().map_or("", String::as_str))
+    }
+
+    /// Operations supported by this cleaner, in execution order
+    const OPERATIONS: [Operation; 12] = [
+        Operation::TemporaryObjects,
+        Operation::KillConnections,
+        Operation::PreparedTransactions,
+        Operation::ReplicationSlots,
+        Operation::Vacuum,
+        Operation::Reindex,
+        Operation::Cluster,
+        Operation::Bloat,
+        Operation::WalAndLogs,
+        Operation::Analyze,
+        Operation::ResetStatistics,
+        Operation::ResetQueryStatistics,
+    ];
+
+    /// Execute the cleaning process into a single function to avoid query repetition
+    async fn run(
+        &self,
+        pool: &Pool<Postgres>,
+        database: &str,
+        report: &mut CleanReport,
+    ) -> Result<(), String> {
+        let schemas: Option<Vec<String>> = self.config.postgres_schemas();
+        let all_tables: Vec<PgTable> = Self::get_all_tables(pool, schemas.clone())
+            .await
+            .map_err(|e| format!("Error fetching tables: {e}"))?;
+        let first_step: usize = report.steps.len();
+        let mut vacuumed: HashSet<String> = HashSet::new();
+
+        for operation in Self::OPERATIONS {
+            if !self.config.is_operation_enabled(operation) {
+                report.add_step(StepReport::skipped(
+                    operation,
+                    Some(database),
+                    "disabled in configuration",
+                ));
+                continue;
+            }
+            if let Some(reason) = self.standby_skip_reason() {
+                report.add_step(StepReport::skipped(operation, Some(database), reason));
+                continue;
+            }
+            if self.deadline.is_reached() {
+                log_and_print(
+                    &format!("Skipping {operation} on {database}: {}", self.deadline.reason()),
+                    &LogType::Warning,
+                );
+                report.add_step(StepReport::skipped(
+                    operation,
+                    Some(database),
+                    self.deadline.reason(),
+                ));
+                continue;
+            }
+            if let Some(reason) = self.online_skip_reason(operation) {
+                print_message(&format!("Skipping {operation} on {database}: {reason}"));
+                report.add_step(StepReport::skipped(operation, Some(database), reason));
+                continue;
+            }
+            let mut step: StepReport = StepReport::new(operation, Some(database));
+            let result = self
+                .run_step(pool, operation, schemas.as_deref(), &all_tables, &mut vacuumed, &mut step)
+                .await;
+            report.add_step(step.finish(result));
+        }
+
+        if self.config.retry_locked {
+            self.retry_locked(pool, database, report, first_step).await;
+        }
         Ok(())
     }
 
+    /// Retry the tables skipped because they were locked, once every step of the database is done
+    async fn retry_locked(&self, pool: &Pool<Postgres>, database: &str, report: &mut CleanReport, first_step: usize) {
+        for (operation, statements) in report.take_locked(first_step) {
+            print_message(&format!("Retrying {operation} on {} locked tables...", statements.len()));
+            let mut step: StepReport = StepReport::new(operation, Some(database));
+            step.detail = Some(String::from("retry of the locked tables"));
+            self.execute_on_tables(pool, &mut step, statements).await;
+            report.add_step(step.finish(Ok(())));
+        }
+    }
+
+    /// Execute a single cleaning step
+    /// `vacuumed` holds the tables already vacuumed by a previous step of the run on this database
+    async fn run_step(
+        &self,
+        pool: &Pool<Postgres>,
+        operation: Operation,
+        schemas: Option<&[String]>,
+        all_tables: &[PgTable],
+        vacuumed: &mut HashSet<String>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        match operation {
+            Operation::TemporaryObjects => {
+                print_message("Cleaning temporary objects...");
+                self.drop_temp_tables(pool, step).await
+            }
+            Operation::KillConnections => {
+                print_message("Terminating sessions idle in transaction...");
+                self.terminate_idle_sessions(pool, step).await
+            }
+            Operation::PreparedTransactions => {
+                print_message("Cleaning stale prepared transactions...");
+                self.clean_prepared_transactions(pool, step).await
+            }
+            Operation::ReplicationSlots => {
+                print_message("Dropping inactive replication slots...");
+                self.drop_replication_slots(pool, step).await
+            }
+            Operation::Vacuum => {
+                print_message("Cleaning dead rows and updating statistics...");
+                self.vacuum_databases(pool, schemas, vacuumed, step).await
+            }
+            Operation::Reindex => {
+                print_message("Reindexing all tables...");
+                self.reindex_all_tables(pool, all_tables, step).await
+            }
+            Operation::Cluster => {
+                print_message("Optimizing table storage layout...");
+                self.cluster_tables(pool, all_tables, step).await
+            }
+            Operation::Bloat => {
+                print_message("Cleaning bloated tables and indexes...");
+                self.clean_bloat(pool, schemas, vacuumed, step).await
+            }
+            Operation::WalAndLogs => {
+                print_message("Checkpointing the WAL...");
+                self.clean_wal_and_logs(pool, step).await
+            }
+            Operation::Analyze => {
+                print_message("Updating global statistics...");
+                self.update_statistics(pool, step).await
+            }
+            Operation::ResetStatistics => {
+                print_message("Resetting statistics...");
+                self.reset_statistics(pool, step).await
+            }
+            Operation::ResetQueryStatistics => {
+                print_message("Resetting pg_stat_statements...");
+                self.reset_query_statistics(pool, step).await
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Execute the REINDEX command on all tables in the database
     /// REINDEX rebuilds one or more indices in a database, improving query performance
+    /// REINDEX CONCURRENTLY is used on `PostgreSQL` 12 and later, so that writes are not blocked
     #[inline]
     async fn reindex_all_tables(
         &self,
         pool: &Pool<Postgres>,
-        all_tables: &[PgRow],
+        all_tables: &[PgTable],
+        step: &mut StepReport,
     ) -> Result<(), Box<dyn Error>> {
-        // REINDEX TABLE is more efficient than REINDEX DATABASE
-        for row in all_tables {
-            let table_name: String = row.get("tablename");
-            let reindex_sql = format!("REINDEX TABLE {table_name}");
-            if let Err(e) = sqlx::query(&reindex_sql).execute(pool).await {
-                log_and_print(
-                    &format!("Error reindexing table {table_name}: {e}"),
-                    &LogType::Warning,
-                );
+        let reindex: &str = match Self::reindex_statement(self.server(), self.online_only()) {
+            Some(reindex) => reindex,
+            None => {
+                for table in all_tables {
+                    step.skip_table(&table.to_string(), "REINDEX CONCURRENTLY needs PostgreSQL 12");
+                }
+                return Ok(());
             }
-        }
+        };
+        // REINDEX TABLE is more efficient than REINDEX DATABASE
+        let statements: Vec<(String, String)> = all_tables
+            .iter()
+            .map(|table| (table.to_string(), format!("{reindex} {}", table.quoted())))
+            .collect();
+        self.execute_on_tables(pool, step, statements).await;
         Ok(())
     }
 
-    /// Execute VACUUM FULL with ANALYZE on all tables
-    /// This reclaims storage and updates statistics in a single operation
-    /// VACUUM FULL requires an exclusive lock but provides maximum space reclamation
+    /// Detect whether the server is a standby with `pg_is_in_recovery()`
+    /// Returns a warning when it is, the steps being skipped unless `allow_on_replica` is set
+    async fn detect_standby(&self, pool: &Pool<Postgres>) -> Option<String> {
+        let standby: bool = sqlx::query_scalar("SELECT pg_is_in_recovery()")
+            .fetch_one(pool)
+            .await
+            .unwrap_or(false);
+        let _ = self.standby.set(standby);
+        if !standby {
+            None
+        } else if self.config.allow_on_replica {
+            Some(String::from("The server is a standby in recovery, steps are run because allow_on_replica is set"))
+        } else {
+            Some(String::from("The server is a standby in recovery, every step is skipped"))
+        }
+    }
+
+    /// Why no step may run on this server, None if it is not a standby or `allow_on_replica` is set
+    #[inline]
+    fn standby_skip_reason(&self) -> Option<&'static str> {
+        let standby: bool = self.standby.get().copied().unwrap_or(false);
+        (standby && !self.config.allow_on_replica).then_some("the server is a standby in recovery")
+    }
+
+    /// Returns true if no statement taking an exclusive lock may run
+    #[inline]
+    const fn online_only(&self) -> bool {
+        self.config.online_only
+    }
+
+    /// Why a step must not run in online-only mode, None if it may run
+    #[inline]
+    const fn online_skip_reason(&self, operation: Operation) -> Option<&'static str> {
+        match operation {
+            Operation::Cluster if self.online_only() => Some("CLUSTER takes an exclusive lock (online_only)"),
+            _ => None,
+        }
+    }
+
+    /// REINDEX statement supported by the server, None when only the blocking one exists but is not allowed
+    #[inline]
+    const fn reindex_statement(server: PgVersion, online_only: bool) -> Option<&'static str> {
+        if server.has_reindex_concurrently() {
+            Some("REINDEX TABLE CONCURRENTLY")
+        } else if online_only {
+            None
+        } else {
+            Some("REINDEX TABLE")
+        }
+    }
+
+    /// VACUUM statement of a table, with the options supported by the server
+    #[inline]
+    fn vacuum_statement(&self, table: &PgTable, full: bool) -> String {
+        // With a lock timeout, a locked table must fail to be reported as locked and retried
+        let skip_locked: bool = self.config.pool.lock_timeout.is_none();
+        let options: String = self.server().vacuum_options(
+            full,
+            self.config.postgres.vacuum_parallel_workers,
+            skip_locked,
+        );
+        format!("VACUUM ({options}) {}", table.quoted())
+    }
+
+    /// Vacuum the tables according to their estimated bloat
+    /// VACUUM FULL reclaims the most storage but requires an exclusive lock, so it is only used on
+    /// tables where the wasted space is above the thresholds, the others get a plain VACUUM
+    /// In online-only mode, the tables worth a VACUUM FULL get a plain VACUUM instead
     #[inline]
     async fn vacuum_databases(
         &self,
         pool: &Pool<Postgres>,
-        all_tables: &[PgRow],
+        schemas: Option<&[String]>,
+        vacuumed: &mut HashSet<String>,
+        step: &mut StepReport,
     ) -> Result<(), Box<dyn Error>> {
-        for row in all_tables {
-            let table_name: String = row.get("tablename");
-            // VACUUM (FULL, ANALYZE, VERBOSE) combines operations for efficiency
-            let vacuum_sql = format!("VACUUM (FULL, ANALYZE) {table_name}");
-            if let Err(e) = sqlx::query(&vacuum_sql).execute(pool).await {
-                log_and_print(
-                    &format!("Error vacuuming table {table_name}: {e}"),
-                    &LogType::Warning,
-                );
+        let estimates: Vec<TableBloat> = self.estimate_bloat(pool, schemas.map(<[String]>::to_vec)).await?;
+        let mut statements: Vec<(String, String)> = Vec::with_capacity(estimates.len());
+
+        for bloat in &estimates {
+            let table: PgTable = PgTable::new(&bloat.schema, &bloat.table);
+            match bloat.vacuum_action(&self.config.bloat) {
+                VacuumAction::Full if self.online_only() => {
+                    Self::print_bloat(bloat, "VACUUM (online only)");
+                    statements.push((table.to_string(), self.vacuum_statement(&table, false)));
+                }
+                VacuumAction::Full => {
+                    Self::print_bloat(bloat, "VACUUM FULL");
+                    statements.push((table.to_string(), self.vacuum_statement(&table, true)));
+                }
+                VacuumAction::Plain => {
+                    Self::print_bloat(bloat, "VACUUM");
+                    statements.push((table.to_string(), self.vacuum_statement(&table, false)));
+                }
+                VacuumAction::Skip => step.skip_table(&table.to_string(), "below bloat thresholds"),
             }
         }
+
+        self.execute_on_tables(pool, step, statements).await;
+        Self::mark_vacuumed(step, vacuumed);
+
+        for bloat in &estimates {
+            step.set_reclaimable_bytes(&PgTable::new(&bloat.schema, &bloat.table).to_string(), bloat.wasted_bytes());
+        }
         Ok(())
     }
 
+    /// Remember the tables successfully vacuumed by a step
+    fn mark_vacuumed(step: &StepReport, vacuumed: &mut HashSet<String>) {
+        vacuumed.extend(
+            step.tables
+                .iter()
+                .filter(|table| table.status == StepStatus::Success)
+                .map(|table| table.table.clone()),
+        );
+    }
+
+    /// Print the bloat estimate of a table and the statement chosen for it
+    fn print_bloat(bloat: &TableBloat, action: &str) {
+        print_message(&format!(
+            "Table {}.{}: ~{BLUE}{}{RESET} bytes reclaimable ({:.0}% bloat, {:.0}% dead tuples) -> {action}",
+            bloat.schema,
+            bloat.table,
+            bloat.wasted_bytes().to_formatted_string(&Locale::en),
+            bloat.bloat_ratio() * 100.0,
+            bloat.dead_tuple_ratio() * 100.0
+        ));
+    }
+
+    /// Estimate the bloat of the user tables in the given schemas, or in all non-system schemas
+    async fn estimate_bloat(
+        &self,
+        pool: &Pool<Postgres>,
+        schemas: Option<Vec<String>>,
+    ) -> Result<Vec<TableBloat>, sqlx::Error> {
+        let rows: Vec<PgRow> = sqlx::query(TableBloat::ESTIMATE_SQL)
+            .bind(schemas)
+            .fetch_all(pool)
+            .await?;
+        rows.iter().map(TableBloat::from_row).collect()
+    }
+
     /// Cluster tables to physically reorder them based on their primary index
     /// This improves query performance by organizing data on disk
     #[inline]
     async fn cluster_tables(
         &self,
         pool: &Pool<Postgres>,
-        all_tables: &[PgRow],
+        all_tables: &[PgTable],
+        step: &mut StepReport,
     ) -> Result<(), Box<dyn Error>> {
-        for row in all_tables {
-            let table_name: String = row.get("tablename");
+        for table in all_tables {
+            let table_name: String = table.to_string();
             // CLUSTER reorganizes the table based on an index
             // Skip if no suitable index exists
-            let cluster_sql = format!("CLUSTER {table_name}");
-            if let Err(e) = sqlx::query(&cluster_sql).execute(pool).await {
-                // Clustering may fail if no index exists, which is okay
-                log_and_print(
-                    &format!("Table {table_name} has no cluster index (skipped): {e}"),
-                    &LogType::Info,
-                );
+            if self.deadline.is_reached() {
+                step.skip_table(&table_name, self.deadline.reason());
+                continue;
+            }
+            let cluster_sql = format!("CLUSTER {}", table.quoted());
+            let start: Instant = Instant::now();
+            match self.execute(pool, &cluster_sql).await {
+                Ok(()) => step.add_table(&table_name, start.elapsed(), Ok(())),
+                Err(e) if is_lock_timeout(&e) => step.add_statement(&table_name, &cluster_sql, start.elapsed(), Err(e)),
+                Err(e) => {
+                    // Clustering may fail if no index exists, which is okay
+                    log_and_print(
+                        &format!("Table {table_name} has no cluster index (skipped): {e}"),
+                        &LogType::Info,
+                    );
+                    step.skip_table(&table_name, "no cluster index");
+                }
             }
         }
         Ok(())
@@ -181,11 +683,15 @@ impl PostgresCleaner {
 
     /// Drop temporary tables created during the cleaning process
     #[inline]
-    async fn drop_temp_tables(&self, pool: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    async fn drop_temp_tables(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
         const SQL: &str = "DROP TABLE IF EXISTS pg_temp CASCADE;";
-        if let Err(e) = sqlx::query(SQL).execute(pool).await {
-            log_and_print(
-                &format!("Error dropping temporary tables: {e}"),
+        if let Err(e) = self.execute(pool, SQL).await {
+            step.warn_and_print(
+                format!("Error dropping temporary tables: {e}"),
                 &LogType::Error,
             );
         }
@@ -193,28 +699,48 @@ impl PostgresCleaner {
     }
 
     /// Clean up old prepared transactions that are stuck
-    /// Prepared transactions can accumulate and cause bloat
+    /// Prepared transactions can accumulate and cause bloat, but an XA coordinator may still commit them,
+    /// so only the transactions of this database older than `min_age` and selected by the gid patterns are rolled back
     #[inline]
-    async fn clean_prepared_transactions(&self, pool: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
-        // Find and rollback prepared transactions older than 1 hour
-        const QUERY: &str = "SELECT gid FROM pg_prepared_xacts WHERE prepared < NOW() - INTERVAL '1 hour'";
+    async fn clean_prepared_transactions(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        const DEFAULT_MIN_AGE: Duration = Duration::from_secs(3600);
+        const QUERY: &str = "SELECT gid, owner::TEXT AS owner, \
+                             EXTRACT(EPOCH FROM NOW() - prepared)::BIGINT AS age_seconds \
+                             FROM pg_prepared_xacts \
+                             WHERE database = current_database() AND prepared < NOW() - make_interval(secs => $1) \
+                             ORDER BY prepared;";
 
-        match sqlx::query(QUERY).fetch_all(pool).await {
+        let settings = &self.config.prepared_transactions;
+        let min_age: Duration = settings.min_age.unwrap_or(DEFAULT_MIN_AGE);
+        match sqlx::query(QUERY).bind(min_age.as_secs_f64()).fetch_all(pool).await {
             Ok(rows) => {
                 for row in rows {
                     let gid: String = row.get("gid");
-                    let rollback_sql = format!("ROLLBACK PREPARED '{gid}'");
-                    if let Err(e) = sqlx::query(&rollback_sql).execute(pool).await {
-                        log_and_print(
-                            &format!("Error rolling back prepared transaction {gid}: {e}"),
+                    if !settings.gids.matches(&gid) {
+                        continue;
+                    }
+                    let owner: String = row.get("owner");
+                    let age_seconds: i64 = row.get("age_seconds");
+                    let rollback_sql = format!("ROLLBACK PREPARED {}", Dialect::Postgres.quote_literal(&gid));
+                    let transaction: String =
+                        format!("prepared transaction {gid} (owner {owner}, prepared {age_seconds}s ago)");
+                    match self.execute(pool, &rollback_sql).await {
+                        Ok(()) if self.config.dry_run => step.dry_run_remove("roll back", transaction),
+                        Ok(()) => step.remove(transaction),
+                        Err(e) => step.warn_and_print(
+                            format!("Error rolling back prepared transaction {gid}: {e}"),
                             &LogType::Warning,
-                        );
+                        ),
                     }
                 }
             }
             Err(e) => {
-                log_and_print(
-                    &format!("Error fetching prepared transactions: {e}"),
+                step.warn_and_print(
+                    format!("Error fetching prepared transactions: {e}"),
                     &LogType::Warning,
                 );
             }
@@ -224,48 +750,55 @@ impl PostgresCleaner {
 
     /// Update PostgreSQL statistics for better query planning
     #[inline]
-    async fn update_statistics(&self, pool: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
+    async fn update_statistics(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
         // Update pg_statistic for better query optimization
         const ANALYZE_ALL: &str = "ANALYZE";
-        if let Err(e) = sqlx::query(ANALYZE_ALL).execute(pool).await {
-            log_and_print(
-                &format!("Error updating statistics: {e}"),
+        if let Err(e) = self.execute(pool, ANALYZE_ALL).await {
+            step.warn_and_print(
+                format!("Error updating statistics: {e}"),
                 &LogType::Warning,
             );
         }
         Ok(())
     }
 
-    /// Clean table and index bloat by identifying and removing dead space
+    /// Clean table and index bloat by vacuuming the tables with too many dead tuples
+    /// Only the configured schemas are processed, and the tables already vacuumed in this run are skipped
     #[inline]
-    async fn clean_bloat(&self, pool: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
-        // Identify tables with significant bloat (>20% wasted space)
-        const BLOAT_QUERY: &str = r#"
-            SELECT schemaname, tablename
-            FROM pg_tables
-            WHERE schemaname NOT IN ('pg_catalog', 'information_schema')
-        "#;
-
-        match sqlx::query(BLOAT_QUERY).fetch_all(pool).await {
-            Ok(rows) => {
-                for row in rows {
-                    let schema: String = row.get("schemaname");
-                    let table: String = row.get("tablename");
-                    let full_table = format!("{schema}.{table}");
-
-                    // VACUUM ANALYZE removes bloat without full table lock
-                    let vacuum_sql = format!("VACUUM ANALYZE {full_table}");
-                    if let Err(e) = sqlx::query(&vacuum_sql).execute(pool).await {
-                        log_and_print(
-                            &format!("Error cleaning bloat for {full_table}: {e}"),
-                            &LogType::Warning,
-                        );
+    async fn clean_bloat(
+        &self,
+        pool: &Pool<Postgres>,
+        schemas: Option<&[String]>,
+        vacuumed: &mut HashSet<String>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        match self.estimate_bloat(pool, schemas.map(<[String]>::to_vec)).await {
+            Ok(estimates) => {
+                let mut statements: Vec<(String, String)> = Vec::with_capacity(estimates.len());
+                for bloat in &estimates {
+                    let table: PgTable = PgTable::new(&bloat.schema, &bloat.table);
+                    if vacuumed.contains(&table.to_string()) {
+                        step.skip_table(&table.to_string(), "already vacuumed in this run");
+                        continue;
+                    }
+                    if !bloat.has_dead_tuples(&self.config.bloat) {
+                        step.skip_table(&table.to_string(), "below bloat thresholds");
+                        continue;
                     }
+                    Self::print_bloat(bloat, "VACUUM ANALYZE");
+                    // VACUUM ANALYZE removes bloat without full table lock
+                    statements.push((table.to_string(), self.vacuum_statement(&table, false)));
                 }
+                self.execute_on_tables(pool, step, statements).await;
+                Self::mark_vacuumed(step, vacuumed);
             }
             Err(e) => {
-                log_and_print(
-                    &format!("Error fetching bloated tables: {e}"),
+                step.warn_and_print(
+                    format!("Error fetching bloated tables: {e}"),
                     &LogType::Warning,
                 );
             }
@@ -273,76 +806,259 @@ impl PostgresCleaner {
         Ok(())
     }
 
-    /// Clean WAL files and clear old log entries (one-shot operation)
-    #[inline]
-    async fn clean_wal_and_logs(&self, pool: &Pool<Postgres>) -> Result<(), Box<dyn Error>> {
-        // Checkpoint to flush WAL to disk
-        const CHECKPOINT: &str = "CHECKPOINT";
-        if let Err(e) = sqlx::query(CHECKPOINT).execute(pool).await {
-            log_and_print(
-                &format!("Error executing checkpoint: {e}"),
-                &LogType::Warning,
+    /// Drop the inactive logical replication slots selected by the slot settings
+    /// A slot keeps its WAL until a consumer such as a CDC pipeline reads it, so the others are left untouched
+    async fn drop_replication_slots(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let sql: String = ReplicationSlot::inactive_slots_sql(self.server());
+        let slots: Vec<ReplicationSlot> = sqlx::query(&sql)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(ReplicationSlot::from_row)
+            .collect::<Result<_, _>>()?;
+
+        for slot in &slots {
+            if let Some(reason) = slot.keep_reason(&self.config.replication_slots) {
+                print_message(&format!("Keeping replication slot {}: {reason}", slot.name));
+                continue;
+            }
+            let drop_sql = format!(
+                "SELECT pg_drop_replication_slot({})",
+                Dialect::Postgres.quote_literal(&slot.name)
             );
+            match self.execute(pool, &drop_sql).await {
+                Ok(()) if self.config.dry_run => step.dry_run_remove("drop", slot.describe()),
+                Ok(()) => step.remove(slot.describe()),
+                Err(e) => step.warn_and_print(
+                    format!("Error dropping replication slot {}: {e}", slot.name),
+                    &LogType::Warning,
+                ),
+            }
         }
+        Ok(())
+    }
 
-        // Clean up old replication slots if any
-        const CLEAN_SLOTS: &str = r#"
-            SELECT pg_drop_replication_slot(slot_name)
-            FROM pg_replication_slots
-            WHERE active = false AND slot_type = 'logical'
-        "#;
-        if let Err(e) = sqlx::query(CLEAN_SLOTS).execute(pool).await {
-            log_and_print(
-                &format!("No inactive replication slots to clean: {e}"),
-                &LogType::Info,
-            );
+    /// Terminate the sessions of the database idle in a transaction for longer than `min_idle` (opt-in)
+    /// Such sessions hold their locks and prevent VACUUM from removing the rows they may still see
+    #[inline]
+    async fn terminate_idle_sessions(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let settings: &KillConnectionSettings = &self.config.kill_connections;
+        let min_idle: i64 = i64::try_from(IdleConnection::min_idle(settings).as_secs()).unwrap_or(i64::MAX);
+        let sessions: Vec<IdleConnection> = sqlx::query(IdleConnection::POSTGRES_SQL)
+            .bind(min_idle)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(IdleConnection::from_pg_row)
+            .collect::<Result<_, _>>()?;
+
+        for session in &sessions {
+            if let Some(reason) = session.keep_reason(settings, &self.config.username) {
+                print_message(&format!("Keeping {}: {reason}", session.describe()));
+                continue;
+            }
+            if settings.report_only {
+                step.dry_run_remove("terminate", session.describe());
+                continue;
+            }
+            match self.execute(pool, &format!("SELECT pg_terminate_backend({})", session.id)).await {
+                Ok(()) if self.config.dry_run => step.dry_run_remove("terminate", session.describe()),
+                Ok(()) => step.remove(session.describe()),
+                Err(e) => step.warn_and_print(
+                    format!("Error terminating session {}: {e}", session.id),
+                    &LogType::Warning,
+                ),
+            }
         }
+        Ok(())
+    }
 
-        // Clear old pg_stat_statements if the extension is installed
-        const CLEAR_STATS: &str = "SELECT pg_stat_statements_reset()";
-        if let Err(e) = sqlx::query(CLEAR_STATS).execute(pool).await {
-            log_and_print(
-                &format!("pg_stat_statements not available (skipped): {e}"),
-                &LogType::Info,
+    /// Flush the WAL to disk with a checkpoint (one-shot operation)
+    #[inline]
+    async fn clean_wal_and_logs(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        // Checkpoint to flush WAL to disk
+        const CHECKPOINT: &str = "CHECKPOINT";
+        if let Err(e) = self.execute(pool, CHECKPOINT).await {
+            step.warn_and_print(
+                format!("Error executing checkpoint: {e}"),
+                &LogType::Warning,
             );
         }
 
-        // Truncate old data from pg_stat_database
+        Ok(())
+    }
+
+    /// Reset the statistics counters of the current database (opt-in)
+    #[inline]
+    async fn reset_statistics(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
         const RESET_STATS: &str = "SELECT pg_stat_reset()";
-        if let Err(e) = sqlx::query(RESET_STATS).execute(pool).await {
-            log_and_print(
-                &format!("Error resetting statistics: {e}"),
+        if let Err(e) = self.execute(pool, RESET_STATS).await {
+            step.warn_and_print(
+                format!("Error resetting statistics: {e}"),
                 &LogType::Warning,
             );
         }
-
         Ok(())
     }
 
-    /// Get all tables that need to be reindexed in `PostgreSQL`
+    /// Reset the statistics gathered by pg_stat_statements (opt-in)
     #[inline]
-    pub fn get_all_postgres_tables_sql(schema: &str) -> String {
-        if schema == "*" {
-            return String::from(
-                "SELECT tablename FROM pg_catalog.pg_tables WHERE schemaname = 'public';",
+    async fn reset_query_statistics(
+        &self,
+        pool: &Pool<Postgres>,
+        step: &mut StepReport,
+    ) -> Result<(), Box<dyn Error>> {
+        const CLEAR_STATS: &str = "SELECT pg_stat_statements_reset()";
+        if let Err(e) = self.execute(pool, CLEAR_STATS).await {
+            step.warn_and_print(
+                format!("pg_stat_statements not available (skipped): {e}"),
+                &LogType::Info,
             );
         }
-        let mut query_all_tables: String =
-            String::from("SELECT tablename FROM pg_catalog.pg_tables WHERE schemaname IN (");
-        query_all_tables.push_str(merge_schema(schema).as_str());
-        query_all_tables.push_str(");");
-        query_all_tables
+        Ok(())
     }
 
-    /// Get the size of the database in bytes
-    #[inline]
-    async fn get_size_of_database(&self, pool: &Pool<Postgres>) -> Result<i64, Box<dyn Error>> {
-        const QUERY: &str =
-            "SELECT SUM(pg_database_size(datname))::BIGINT AS total_size_bytes FROM pg_database;";
-        let row: (i64,) = sqlx::query_as(QUERY)
-            .bind(&self.config.schema)
+    /// Get all tables that need to be reindexed in `PostgreSQL`, in the schemas bound to `$1`
+    /// or in all non-system schemas when it is NULL
+    pub const ALL_TABLES_SQL: &str = "SELECT schemaname::TEXT AS schemaname, tablename::TEXT AS tablename \
+                                      FROM pg_catalog.pg_tables \
+                                      WHERE CASE WHEN $1::TEXT[] IS NULL \
+                                      THEN schemaname NOT IN ('pg_catalog', 'information_schema') \
+                                      AND schemaname NOT LIKE 'pg\\_temp\\_%' \
+                                      ELSE schemaname = ANY($1) END \
+                                      ORDER BY schemaname, tablename;";
+
+    /// Get the schema-qualified tables of the given schemas, or of all non-system schemas
+    async fn get_all_tables(
+        pool: &Pool<Postgres>,
+        schemas: Option<Vec<String>>,
+    ) -> Result<Vec<PgTable>, sqlx::Error> {
+        let rows: Vec<PgRow> = sqlx::query(Self::ALL_TABLES_SQL)
+            .bind(schemas)
+            .fetch_all(pool)
+            .await?;
+        rows.iter().map(PgTable::from_row).collect()
+    }
+
+    /// Check the privileges of the user and the capabilities of the server for the planned steps
+    async fn preflight(&self, pool: &Pool<Postgres>) -> Result<Preflight, sqlx::Error> {
+        const PRIVILEGES_SQL: &str = "SELECT current_setting('server_version') AS version, \
+             r.rolsuper AS superuser, r.rolreplication AS replication, \
+             EXISTS(SELECT 1 FROM pg_roles g WHERE g.rolname = 'pg_checkpoint' \
+             AND pg_has_role(current_user, g.oid, 'MEMBER')) AS checkpoint_role, \
+             EXISTS(SELECT 1 FROM pg_roles g WHERE g.rolname = 'pg_maintain' \
+             AND pg_has_role(current_user, g.oid, 'MEMBER')) AS maintain_role, \
+             pg_has_role(current_user, 'pg_signal_backend', 'MEMBER') AS signal_role, \
+             has_function_privilege('pg_stat_reset()', 'EXECUTE') AS stat_reset \
+             FROM pg_roles r WHERE r.rolname = current_user;";
+        const OWNED_TABLES_SQL: &str = "SELECT COUNT(*)::BIGINT AS tables, \
+             (COUNT(*) FILTER (WHERE pg_has_role(current_user, c.relowner, 'USAGE')))::BIGINT AS owned \
+             FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE c.relkind IN ('r', 'p') AND CASE WHEN $1::TEXT[] IS NULL \
+             THEN n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg\\_%' \
+             ELSE n.nspname = ANY($1) END;";
+        const EXTENSIONS_SQL: &str = "SELECT extname::TEXT FROM pg_extension ORDER BY extname;";
+
+        let row: PgRow = sqlx::query(PRIVILEGES_SQL).fetch_one(pool).await?;
+        let superuser: bool = row.try_get("superuser")?;
+        let replication: bool = row.try_get("replication")?;
+        let checkpoint_role: bool = row.try_get("checkpoint_role")?;
+        let maintain_role: bool = row.try_get("maintain_role")?;
+        let signal_role: bool = row.try_get("signal_role")?;
+        let stat_reset: bool = row.try_get("stat_reset")?;
+        let (tables, owned): (i64, i64) = sqlx::query_as(OWNED_TABLES_SQL)
+            .bind(self.config.postgres_schemas())
             .fetch_one(pool)
             .await?;
+        let extensions: Vec<String> = sqlx::query_scalar(EXTENSIONS_SQL).fetch_all(pool).await?;
+
+        let mut preflight: Preflight = Preflight::new(row.try_get("version")?);
+        if superuser {
+            preflight.capabilities.push(String::from("superuser"));
+        }
+        if replication {
+            preflight.capabilities.push(String::from("replication"));
+        }
+        preflight.capabilities.extend(extensions.iter().map(|extension| format!("extension {extension}")));
+
+        // VACUUM, REINDEX, CLUSTER and ANALYZE only process the tables owned by the user
+        let (table_issues, no_table): (Vec<String>, bool) = if superuser || maintain_role || owned == tables {
+            (Vec::new(), false)
+        } else {
+            (vec![format!("the user owns {owned} of the {tables} tables")], owned == 0)
+        };
+
+        for operation in Self::OPERATIONS {
+            if !self.config.is_operation_enabled(operation) {
+                continue;
+            }
+            match operation {
+                Operation::Vacuum | Operation::Reindex | Operation::Cluster | Operation::Bloat | Operation::Analyze => {
+                    preflight.check(operation, table_issues.clone(), no_table);
+                }
+                Operation::KillConnections if !superuser && !signal_role => {
+                    let issues: Vec<String> = vec![String::from(
+                        "terminating the sessions of other roles needs superuser or pg_signal_backend",
+                    )];
+                    preflight.check(operation, issues, false);
+                }
+                Operation::PreparedTransactions => {
+                    let issues: Vec<String> = if superuser {
+                        Vec::new()
+                    } else {
+                        vec![String::from("only the transactions prepared by this user can be rolled back")]
+                    };
+                    preflight.check(operation, issues, false);
+                }
+                Operation::ReplicationSlots => {
+                    let issues: Vec<String> = if superuser || replication {
+                        Vec::new()
+                    } else {
+                        vec![String::from("dropping replication slots needs superuser or REPLICATION")]
+                    };
+                    let blocking: bool = !issues.is_empty();
+                    preflight.check(operation, issues, blocking);
+                }
+                Operation::WalAndLogs if !superuser && !checkpoint_role => {
+                    preflight.check(operation, vec![String::from("CHECKPOINT needs superuser or pg_checkpoint")], true);
+                }
+                Operation::ResetStatistics if !stat_reset => {
+                    preflight.check(operation, vec![String::from("no EXECUTE privilege on pg_stat_reset()")], true);
+                }
+                Operation::ResetQueryStatistics
+                    if !extensions.iter().any(|extension| extension == "pg_stat_statements") =>
+                {
+                    preflight.check(operation, vec![String::from("pg_stat_statements is not installed")], true);
+                }
+                _ => preflight.check(operation, Vec::new(), false),
+            }
+        }
+        Ok(preflight)
+    }
+
+    /// Get the size in bytes of the given databases, or of every database but the templates when None
+    #[inline]
+    async fn get_size_of_database(pool: &Pool<Postgres>, databases: Option<&[String]>) -> Result<i64, Box<dyn Error>> {
+        const QUERY: &str = "SELECT COALESCE(SUM(pg_database_size(datname)), 0)::BIGINT AS total_size_bytes \
+             FROM pg_database WHERE datname NOT IN ('template0', 'template1') \
+             AND ($1::TEXT[] IS NULL OR datname = ANY($1));";
+        let row: (i64,) = sqlx::query_as(QUERY).bind(databases).fetch_one(pool).await?;
 
         Ok(row.0)
     }
@@ -365,23 +1081,6 @@ impl PostgresCleaner {
         }
         Ok(databases)
     }
-
-    /// Loop through all tables and execute a query
-    #[inline]
-    pub async fn loop_and_execute_query_postgres(
-        pool: &Pool<Postgres>,
-        all_tables: &[PgRow],
-        command: &str,
-    ) {
-        const QUERY_INDEX: &str = "all_tables";
-        for row in all_tables {
-            let table_name: String = row.get(QUERY_INDEX);
-            let analyze_sql: String = format!("{command}{table_name}");
-            if let Err(e) = sqlx::query(&analyze_sql).execute(pool).await {
-                log_and_print(&format!("Error executing query: {e}"), &LogType::Error);
-            }
-        }
-    }
 }
 
 #[cfg(test)]
@@ -402,31 +1101,60 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_all_postgres_tables_sql() {
-        let schema: String = String::from("test");
-        let tested_sql: String = PostgresCleaner::get_all_postgres_tables_sql(&schema);
-        let true_sql: String =
-            String::from("SELECT tablename FROM pg_catalog.pg_tables WHERE schemaname IN ('test');");
-        assert_eq!(tested_sql, true_sql);
-
-        let schema: String = String::from("test,test1");
-        let tested_sql: String = PostgresCleaner::get_all_postgres_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT tablename FROM pg_catalog.pg_tables WHERE schemaname IN ('test','test1');",
-        );
-        assert_eq!(tested_sql, true_sql);
+    async fn test_pg_table() {
+        let table: PgTable = PgTable::new("Billing", "order items");
+        assert_eq!(table.to_string(), "Billing.order items");
+        assert_eq!(table.quoted(), "\"Billing\".\"order items\"");
+    }
+
+    #[tokio::test]
+    async fn test_standby_skip_reason() {
+        let mut config: Config = get_test_config(ConnectionEngine::Postgres, "5432");
+        let cleaner: PostgresCleaner = PostgresCleaner::new(config.clone());
+        assert_eq!(cleaner.standby_skip_reason(), None);
+        cleaner.standby.set(true).unwrap();
+        assert!(cleaner.standby_skip_reason().is_some());
+
+        config.allow_on_replica = true;
+        let cleaner: PostgresCleaner = PostgresCleaner::new(config);
+        cleaner.standby.set(true).unwrap();
+        assert_eq!(cleaner.standby_skip_reason(), None);
+    }
+
+    #[tokio::test]
+    async fn test_online_only() {
+        let mut config: Config = get_test_config(ConnectionEngine::Postgres, "5432");
+        config.online_only = true;
+        let cleaner: PostgresCleaner = PostgresCleaner::new(config);
+        assert!(cleaner.online_skip_reason(Operation::Cluster).is_some());
+        assert_eq!(cleaner.online_skip_reason(Operation::Vacuum), None);
+        assert_eq!(PostgresCleaner::reindex_statement(cleaner.server(), cleaner.online_only()), None);
+    }
+
+    #[tokio::test]
+    async fn test_version_specific_statements() {
+        let pg11: PgVersion = PgVersion::parse("110022");
+        let pg16: PgVersion = PgVersion::parse("160002");
+        assert_eq!(PostgresCleaner::reindex_statement(pg16, true), Some("REINDEX TABLE CONCURRENTLY"));
+        assert_eq!(PostgresCleaner::reindex_statement(pg11, false), Some("REINDEX TABLE"));
+        assert_eq!(PostgresCleaner::reindex_statement(pg11, true), None);
 
-        let schema: String = String::from("test,test1,test2");
-        let tested_sql: String = PostgresCleaner::get_all_postgres_tables_sql(&schema);
-        let true_sql: String = String::from(
-            "SELECT tablename FROM pg_catalog.pg_tables WHERE schemaname IN ('test','test1','test2');",
+        let mut config: Config = get_test_config(ConnectionEngine::Postgres, "5432");
+        config.postgres.vacuum_parallel_workers = Some(2);
+        let cleaner: PostgresCleaner = PostgresCleaner::new(config);
+        let table: PgTable = PgTable::new("public", "orders");
+        assert_eq!(cleaner.vacuum_statement(&table, true), "VACUUM (FULL, ANALYZE) \"public\".\"orders\"");
+        cleaner.server.set(pg16).unwrap();
+        assert_eq!(cleaner.online_skip_reason(Operation::Cluster), None);
+        assert_eq!(
+            cleaner.vacuum_statement(&table, false),
+            "VACUUM (ANALYZE, SKIP_LOCKED, INDEX_CLEANUP AUTO, PROCESS_TOAST, PARALLEL 2) \"public\".\"orders\""
         );
-        assert_eq!(tested_sql, true_sql);
 
-        let schema: String = String::from("*");
-        let tested_sql: String = PostgresCleaner::get_all_postgres_tables_sql(&schema);
-        let true_sql: String =
-            String::from("SELECT tablename FROM pg_catalog.pg_tables WHERE schemaname = 'public';");
-        assert_eq!(tested_sql, true_sql);
+        let mut config: Config = get_test_config(ConnectionEngine::Postgres, "5432");
+        config.pool.lock_timeout = Some(Duration::from_secs(5));
+        let cleaner: PostgresCleaner = PostgresCleaner::new(config);
+        cleaner.server.set(pg16).unwrap();
+        assert!(!cleaner.vacuum_statement(&table, true).contains("SKIP_LOCKED"));
     }
 }
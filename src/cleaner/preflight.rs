@@ -0,0 +1,215 @@
+use crate::colors::{GREEN, RED, RESET, YELLOW};
+use crate::config::Operation;
+use crate::logger::print_message;
+use serde::Serialize;
+
+/// Whether a planned step is expected to work with the privileges of the connected user
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum Availability {
+    Ready,
+    /// Some statements of the step will fail or be skipped
+    Partial,
+    /// Nothing in the step can succeed
+    Unavailable,
+}
+
+impl Availability {
+    /// Returns the string representation of the availability
+    #[inline]
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ready => "ready",
+            Self::Partial => "partial",
+            Self::Unavailable => "unavailable",
+        }
+    }
+}
+
+/// Expected outcome of a planned step
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct StepCheck {
+    pub operation: Operation,
+    pub availability: Availability,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+}
+
+/// Privileges and capabilities of a server, checked before any mutating statement runs
+#[derive(Serialize, Debug, Clone, Default)]
+#[must_use]
+pub struct Preflight {
+    pub server_version: String,
+    /// Capabilities found on the server, such as extensions and storage engines
+    pub capabilities: Vec<String>,
+    pub checks: Vec<StepCheck>,
+}
+
+impl Preflight {
+    /// Start a preflight for the given server version
+    pub fn new(server_version: String) -> Self {
+        Self {
+            server_version,
+            ..Default::default()
+        }
+    }
+
+    /// Record the issues of a planned step
+    /// `blocking` tells if the issues prevent the whole step from working, or only part of it
+    pub fn check(&mut self, operation: Operation, issues: Vec<String>, blocking: bool) {
+        let availability: Availability = match (issues.is_empty(), blocking) {
+            (true, _) => Availability::Ready,
+            (false, false) => Availability::Partial,
+            (false, true) => Availability::Unavailable,
+        };
+        self.checks.push(StepCheck {
+            operation,
+            availability,
+            issues,
+        });
+    }
+
+    /// Steps that will not fully work, with their issues joined, used as warnings of the report
+    #[must_use]
+    pub fn warnings(&self) -> Vec<String> {
+        self.checks
+            .iter()
+            .filter(|check| check.availability != Availability::Ready)
+            .map(|check| {
+                format!(
+                    "Preflight: {} is {}: {}",
+                    check.operation,
+                    check.availability.as_str(),
+                    check.issues.join("; ")
+                )
+            })
+            .collect()
+    }
+
+    /// Print the matrix of the planned steps and their availability
+    pub fn print(&self) {
+        print_message(&format!("Server version: {}", self.server_version));
+        if !self.capabilities.is_empty() {
+            print_message(&format!("Capabilities: {}", self.capabilities.join(", ")));
+        }
+        print_message("Preflight:");
+        for check in &self.checks {
+            let color: &str = match check.availability {
+                Availability::Ready => GREEN,
+                Availability::Partial => YELLOW,
+                Availability::Unavailable => RED,
+            };
+            print_message(&format!(
+                "  {:<22} {color}{:<11}{RESET} {}",
+                check.operation.as_str(),
+                check.availability.as_str(),
+                check.issues.join("; ")
+            ));
+        }
+    }
+}
+
+/// Global and per-schema privileges of a `MySQL` user, parsed from `SHOW GRANTS`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[must_use]
+pub struct MySqlGrants {
+    /// Upper-case privilege names with the schema they apply to, None for `*.*`
+    grants: Vec<(String, Option<String>)>,
+}
+
+impl MySqlGrants {
+    /// Parse the lines returned by `SHOW GRANTS`, such as GRANT SELECT, RELOAD ON *.* TO `u`@`%`
+    pub fn parse<S: AsRef<str>>(lines: &[S]) -> Self {
+        let mut grants: Vec<(String, Option<String>)> = Vec::new();
+        for line in lines {
+            let line: &str = line.as_ref();
+            let Some(rest) = line.strip_prefix("GRANT ") else {
+                continue;
+            };
+            let Some((privileges, rest)) = rest.split_once(" ON ") else {
+                continue;
+            };
+            let Some(target) = rest.split_whitespace().next() else {
+                continue;
+            };
+            let schema: Option<String> = match target.split_once('.') {
+                Some(("*", "*")) => None,
+                Some((schema, _)) => Some(schema.trim_matches('`').replace("``", "`")),
+                None => continue,
+            };
+            for privilege in privileges.split(',') {
+                let privilege: String = privilege.trim().to_ascii_uppercase();
+                let privilege: String = if privilege == "ALL" { String::from("ALL PRIVILEGES") } else { privilege };
+                grants.push((privilege, schema.clone()));
+            }
+        }
+        Self { grants }
+    }
+
+    /// Returns true if the privilege is granted globally, or on the given schema
+    #[must_use]
+    pub fn has(&self, privilege: &str, schema: Option<&str>) -> bool {
+        self.grants.iter().any(|(granted, on)| {
+            // ALL PRIVILEGES does not include the dynamic privileges of MySQL 8
+            let matches: bool = granted == privilege
+                || (granted == "ALL PRIVILEGES" && !privilege.ends_with("_ADMIN"));
+            matches && (on.is_none() || (on.as_deref() == schema && schema.is_some()))
+        })
+    }
+
+    /// Returns true if the privilege is granted globally
+    #[inline]
+    #[must_use]
+    pub fn has_global(&self, privilege: &str) -> bool {
+        self.has(privilege, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preflight_checks() {
+        let mut preflight = Preflight::new(String::from("16.2"));
+        preflight.check(Operation::Vacuum, Vec::new(), true);
+        preflight.check(Operation::WalAndLogs, vec![String::from("CHECKPOINT needs superuser")], false);
+        preflight.check(Operation::Cluster, vec![String::from("no table owned")], true);
+        let availability: Vec<Availability> = preflight.checks.iter().map(|c| c.availability).collect();
+        assert_eq!(
+            availability,
+            vec![Availability::Ready, Availability::Partial, Availability::Unavailable]
+        );
+        assert_eq!(
+            preflight.warnings(),
+            vec![
+                "Preflight: wal_and_logs is partial: CHECKPOINT needs superuser",
+                "Preflight: cluster is unavailable: no table owned",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mysql_grants() {
+        let grants = MySqlGrants::parse(&[
+            "GRANT SELECT, INSERT, RELOAD ON *.* TO `cleaner`@`%`",
+            "GRANT BINLOG_ADMIN ON *.* TO `cleaner`@`%`",
+            "GRANT ALL PRIVILEGES ON `app`.* TO `cleaner`@`%`",
+            "GRANT USAGE ON *.* TO `cleaner`@`%` IDENTIFIED BY PASSWORD '*ABC'",
+        ]);
+        assert!(grants.has_global("RELOAD"));
+        assert!(grants.has_global("BINLOG_ADMIN"));
+        assert!(!grants.has_global("SUPER"));
+        assert!(grants.has("SELECT", Some("billing")));
+        assert!(grants.has("ALTER", Some("app")));
+        assert!(!grants.has("ALTER", Some("billing")));
+        assert!(!grants.has("ALTER", None));
+
+        let root = MySqlGrants::parse(&["GRANT ALL PRIVILEGES ON *.* TO 'root'@'localhost' WITH GRANT OPTION"]);
+        assert!(root.has_global("SUPER") && root.has("DROP", Some("mysql")));
+        assert!(!root.has_global("CONNECTION_ADMIN"));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::cleaner::server_version::PgVersion;
+use crate::config::ReplicationSlotSettings;
+use num_format::{Locale, ToFormattedString};
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+use std::time::Duration;
+
+/// Inactive logical replication slot of a `PostgreSQL` server
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct ReplicationSlot {
+    pub name: String,
+    /// Number of WAL bytes the slot prevents the server from removing
+    pub retained_bytes: i64,
+    /// Seconds since the slot became inactive, None before `PostgreSQL` 17
+    pub inactive_seconds: Option<i64>,
+}
+
+impl ReplicationSlot {
+    /// Inactive logical slots of the current database, `inactive_since` only exists since `PostgreSQL` 17
+    /// A logical slot belongs to one database, the slots of the other databases are left to their own runs
+    /// `pg_current_wal_lsn()` fails during recovery, so a standby measures the retained WAL from its last replayed LSN
+    #[must_use]
+    pub fn inactive_slots_sql(server: PgVersion) -> String {
+        let inactive_seconds: &str = if server.major() >= 17 {
+            "EXTRACT(EPOCH FROM NOW() - inactive_since)::BIGINT"
+        } else {
+            "NULL::BIGINT"
+        };
+        format!(
+            "SELECT slot_name::TEXT AS slot_name, \
+             COALESCE(pg_wal_lsn_diff(CASE WHEN pg_is_in_recovery() THEN pg_last_wal_replay_lsn() \
+             ELSE pg_current_wal_lsn() END, restart_lsn), 0)::BIGINT AS retained_bytes, \
+             {inactive_seconds} AS inactive_seconds \
+             FROM pg_replication_slots \
+             WHERE active = false AND slot_type = 'logical' AND database = current_database() \
+             ORDER BY slot_name;"
+        )
+    }
+
+    /// Read a slot from a row of `inactive_slots_sql`
+    pub fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            name: row.try_get("slot_name")?,
+            retained_bytes: row.try_get("retained_bytes")?,
+            inactive_seconds: row.try_get("inactive_seconds")?,
+        })
+    }
+
+    /// Why the slot must be kept, None if it may be dropped
+    #[must_use]
+    pub fn keep_reason(&self, settings: &ReplicationSlotSettings) -> Option<&'static str> {
+        if !settings.names.matches(&self.name) {
+            return Some("not selected by the slot name patterns");
+        }
+        if self.retained_bytes < settings.min_retained_bytes {
+            return Some("retains less WAL than min_retained_bytes");
+        }
+        let min_inactive_age: Duration = settings.min_inactive_age?;
+        match self.inactive_seconds {
+            None => Some("inactivity unknown before PostgreSQL 17, min_inactive_age cannot be checked"),
+            Some(seconds) if u64::try_from(seconds).unwrap_or(0) < min_inactive_age.as_secs() => {
+                Some("inactive for less than min_inactive_age")
+            }
+            Some(_) => None,
+        }
+    }
+
+    /// Description of the slot recorded in the report once dropped
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let inactive: String = self
+            .inactive_seconds
+            .map(|seconds| format!(", inactive for {seconds}s"))
+            .unwrap_or_default();
+        format!(
+            "replication slot {} ({} bytes of WAL retained{inactive})",
+            self.name,
+            self.retained_bytes.to_formatted_string(&Locale::en)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NameFilter;
+
+    fn slot(name: &str, retained_bytes: i64, inactive_seconds: Option<i64>) -> ReplicationSlot {
+        ReplicationSlot {
+            name: name.to_string(),
+            retained_bytes,
+            inactive_seconds,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_reason() {
+        let settings = ReplicationSlotSettings {
+            min_inactive_age: Some(Duration::from_secs(3600)),
+            min_retained_bytes: 1024,
+            names: NameFilter {
+                include: Vec::new(),
+                exclude: vec![String::from("debezium*")],
+            },
+        };
+        assert_eq!(slot("tmp_export", 4096, Some(7200)).keep_reason(&settings), None);
+        assert!(slot("debezium_orders", 4096, Some(7200)).keep_reason(&settings).is_some());
+        assert!(slot("tmp_export", 512, Some(7200)).keep_reason(&settings).is_some());
+        assert!(slot("tmp_export", 4096, Some(60)).keep_reason(&settings).is_some());
+        assert!(slot("tmp_export", 4096, None).keep_reason(&settings).is_some());
+        assert_eq!(slot("tmp_export", 0, None).keep_reason(&ReplicationSlotSettings::default()), None);
+    }
+
+    #[tokio::test]
+    async fn test_inactive_slots_sql() {
+        for version in ["170000", "120005"] {
+            let sql: String = ReplicationSlot::inactive_slots_sql(PgVersion::parse(version));
+            assert!(sql.contains("AND database = current_database()"));
+            assert!(sql.contains(
+                "CASE WHEN pg_is_in_recovery() THEN pg_last_wal_replay_lsn() ELSE pg_current_wal_lsn() END"
+            ));
+        }
+        assert!(ReplicationSlot::inactive_slots_sql(PgVersion::parse("170000")).contains("inactive_since"));
+        assert!(ReplicationSlot::inactive_slots_sql(PgVersion::parse("160002")).contains("NULL::BIGINT"));
+        assert_eq!(
+            slot("tmp_export", 2048, Some(90)).describe(),
+            "replication slot tmp_export (2,048 bytes of WAL retained, inactive for 90s)"
+        );
+    }
+}
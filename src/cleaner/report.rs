@@ -0,0 +1,453 @@
+use crate::cleaner::preflight::Preflight;
+use crate::config::{Config, Operation};
+use crate::helpers::is_lock_timeout;
+use crate::logger::{log_and_print, print_message, LogType};
+use serde::Serialize;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+/// Outcome of a step or of a table operation
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum StepStatus {
+    Success,
+    Warning,
+    Failed,
+    Skipped,
+}
+
+impl StepStatus {
+    /// Returns the string representation of the status
+    #[inline]
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Warning => "warning",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+impl std::fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Result of a statement executed on a single table
+#[derive(Serialize, Debug, Clone)]
+#[must_use]
+pub struct TableReport {
+    pub table: String,
+    pub status: StepStatus,
+    pub duration_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Estimated number of bytes the statement could give back, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reclaimable_bytes: Option<i64>,
+}
+
+/// Reason recorded for a table skipped because a lock could not be acquired in time
+pub const LOCKED: &str = "locked";
+
+/// Result of a single cleaning step
+#[derive(Serialize, Debug, Clone)]
+#[must_use]
+pub struct StepReport {
+    pub operation: Operation,
+    /// Database or schema the step was executed on, when the cleaner visits several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub status: StepStatus,
+    pub duration_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub tables: Vec<TableReport>,
+    /// Objects dropped, rolled back or terminated by the step, with why they were selected
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+    /// Objects the step would have removed without the dry run
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub would_remove: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Tables skipped because they were locked, with their statement to retry at the end of the run
+    #[serde(skip)]
+    pub locked: Vec<(String, String)>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl StepReport {
+    /// Start a new step
+    pub fn new(operation: Operation, target: Option<&str>) -> Self {
+        Self {
+            operation,
+            target: target.map(String::from),
+            status: StepStatus::Success,
+            duration_ms: 0,
+            detail: None,
+            tables: Vec::new(),
+            removed: Vec::new(),
+            would_remove: Vec::new(),
+            warnings: Vec::new(),
+            locked: Vec::new(),
+            started: Some(Instant::now()),
+        }
+    }
+
+    /// Create a step that was not executed
+    pub fn skipped(operation: Operation, target: Option<&str>, reason: &str) -> Self {
+        let mut step = Self::new(operation, target);
+        step.status = StepStatus::Skipped;
+        step.detail = Some(reason.to_string());
+        step.started = None;
+        step
+    }
+
+    /// Record a warning for this step
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+
+    /// Log and print a message, and record it as a warning for this step
+    pub fn warn_and_print(&mut self, message: String, log_type: &LogType) {
+        log_and_print(&message, log_type);
+        self.warn(message);
+    }
+
+    /// Record the result of a statement executed on a table
+    pub fn add_table(&mut self, table: &str, duration: Duration, result: Result<(), String>) {
+        let (status, detail) = match result {
+            Ok(()) => (StepStatus::Success, None),
+            Err(e) => (StepStatus::Failed, Some(e)),
+        };
+        self.tables.push(TableReport {
+            table: table.to_string(),
+            status,
+            duration_ms: duration.as_millis(),
+            detail,
+            reclaimable_bytes: None,
+        });
+    }
+
+    /// Print and record an object removed by the step
+    pub fn remove(&mut self, object: String) {
+        print_message(&format!("Removed {object}"));
+        self.removed.push(object);
+    }
+
+    /// Print and record an object left in place by the dry run, `action` tells what would have been done to it
+    pub fn dry_run_remove(&mut self, action: &str, object: String) {
+        print_message(&format!("Would {action} {object}"));
+        self.would_remove.push(object);
+    }
+
+    /// Record the result of a statement executed on a table and log its error
+    /// A table whose lock could not be acquired in time is skipped, and its statement kept to be retried
+    pub fn add_statement(&mut self, table: &str, sql: &str, duration: Duration, result: Result<(), sqlx::Error>) {
+        if let Err(e) = &result {
+            let message: String = if is_lock_timeout(e) {
+                format!("Table {table} is locked, {} skipped: {e}", self.operation)
+            } else {
+                format!("Error running {} on table {table}: {e}", self.operation)
+            };
+            log_and_print(&message, &LogType::Warning);
+        }
+        self.record_statement(table, sql, duration, result);
+    }
+
+    /// Record the result of a statement executed on a table, without logging it
+    fn record_statement(&mut self, table: &str, sql: &str, duration: Duration, result: Result<(), sqlx::Error>) {
+        match result {
+            Err(e) if is_lock_timeout(&e) => {
+                self.skip_table(table, LOCKED);
+                self.locked.push((table.to_string(), sql.to_string()));
+            }
+            result => self.add_table(table, duration, result.map_err(|e| e.to_string())),
+        }
+    }
+
+    /// Record a table that was not processed
+    pub fn skip_table(&mut self, table: &str, reason: &str) {
+        self.tables.push(TableReport {
+            table: table.to_string(),
+            status: StepStatus::Skipped,
+            duration_ms: 0,
+            detail: Some(reason.to_string()),
+            reclaimable_bytes: None,
+        });
+    }
+
+    /// Record the estimated number of reclaimable bytes of a table already in the report
+    pub fn set_reclaimable_bytes(&mut self, table: &str, bytes: i64) {
+        if let Some(table_report) = self.tables.iter_mut().rev().find(|t| t.table == table) {
+            table_report.reclaimable_bytes = Some(bytes);
+        }
+    }
+
+    /// Complete the step with the result of its execution
+    pub fn finish(mut self, result: Result<(), Box<dyn Error>>) -> Self {
+        if let Some(started) = self.started.take() {
+            self.duration_ms = started.elapsed().as_millis();
+        }
+
+        if let Err(e) = result {
+            self.status = StepStatus::Failed;
+            self.detail = Some(e.to_string());
+        } else if self.status == StepStatus::Success
+            && (!self.warnings.is_empty()
+                || self.tables.iter().any(|t| t.status == StepStatus::Failed))
+        {
+            self.status = StepStatus::Warning;
+        }
+        self
+    }
+}
+
+/// Result of cleaning a database, returned by `DatabaseCleaner::clean`
+#[derive(Serialize, Debug, Clone)]
+#[must_use]
+pub struct CleanReport {
+    pub name: Option<String>,
+    pub driver: String,
+    pub host: String,
+    pub dry_run: bool,
+    pub start_bytes: i64,
+    pub end_bytes: i64,
+    pub duration_ms: u128,
+    /// Privileges and capabilities checked before cleaning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preflight: Option<Preflight>,
+    pub steps: Vec<StepReport>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl CleanReport {
+    /// Start a new report for the given database
+    pub fn new(config: &Config) -> Self {
+        Self {
+            name: config.name.clone(),
+            driver: config.driver.to_string(),
+            host: format!("{}:{}", config.host, config.port),
+            dry_run: config.dry_run,
+            start_bytes: 0,
+            end_bytes: 0,
+            duration_ms: 0,
+            preflight: None,
+            steps: Vec::new(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            started: Some(Instant::now()),
+        }
+    }
+
+    /// Add a completed step to the report
+    pub fn add_step(&mut self, step: StepReport) {
+        self.steps.push(step);
+    }
+
+    /// Take the locked tables of the steps added since `first_step`, grouped by operation
+    pub fn take_locked(&mut self, first_step: usize) -> Vec<(Operation, Vec<(String, String)>)> {
+        self.steps
+            .iter_mut()
+            .skip(first_step)
+            .filter(|step| !step.locked.is_empty())
+            .map(|step| (step.operation, std::mem::take(&mut step.locked)))
+            .collect()
+    }
+
+    /// Record a warning that is not related to a single step
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+
+    /// Record the preflight of the database, the steps that will not fully work become warnings
+    pub fn set_preflight(&mut self, preflight: Preflight) {
+        self.warnings.extend(preflight.warnings());
+        self.preflight = Some(preflight);
+    }
+
+    /// Record an error that prevented part of the database from being cleaned
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(message.into());
+    }
+
+    /// Complete the report
+    pub fn finish(mut self) -> Self {
+        if let Some(started) = self.started.take() {
+            self.duration_ms = started.elapsed().as_millis();
+        }
+        self
+    }
+
+    /// Number of bytes reclaimed by the cleaning
+    #[must_use]
+    pub const fn reclaimed_bytes(&self) -> i64 {
+        if self.start_bytes > self.end_bytes {
+            self.start_bytes - self.end_bytes
+        } else {
+            0
+        }
+    }
+
+    /// Count the steps with the given status
+    #[must_use]
+    pub fn count_steps(&self, status: StepStatus) -> usize {
+        self.steps.iter().filter(|step| step.status == status).count()
+    }
+
+    /// Count all warnings of the report, including failed tables
+    #[must_use]
+    pub fn warning_count(&self) -> usize {
+        self.warnings.len()
+            + self
+                .steps
+                .iter()
+                .map(|step| {
+                    step.warnings.len()
+                        + step
+                            .tables
+                            .iter()
+                            .filter(|t| t.status == StepStatus::Failed)
+                            .count()
+                })
+                .sum::<usize>()
+    }
+
+    /// A run is healthy when no step failed and no error was recorded
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.errors.is_empty() && self.count_steps(StepStatus::Failed) == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConnectionEngine, tests::get_test_config};
+    use crate::helpers::tests::TestDatabaseError;
+
+    #[test]
+    fn test_step_status() {
+        assert_eq!(StepStatus::Success.as_str(), "success");
+        assert_eq!(StepStatus::Warning.as_str(), "warning");
+        assert_eq!(StepStatus::Failed.as_str(), "failed");
+        assert_eq!(format!("{}", StepStatus::Skipped), "skipped");
+    }
+
+    #[test]
+    fn test_step_report_finish() {
+        let step = StepReport::new(Operation::Vacuum, None).finish(Ok(()));
+        assert_eq!(step.status, StepStatus::Success);
+
+        let mut step = StepReport::new(Operation::Vacuum, Some("app"));
+        step.add_table("public.users", Duration::from_millis(12), Ok(()));
+        step.add_table("public.orders", Duration::ZERO, Err(String::from("locked")));
+        step.set_reclaimable_bytes("public.users", 4096);
+        let step = step.finish(Ok(()));
+        assert_eq!(step.status, StepStatus::Warning);
+        assert_eq!(step.tables[0].duration_ms, 12);
+        assert_eq!(step.tables[1].detail.as_deref(), Some("locked"));
+        assert_eq!(step.tables[0].reclaimable_bytes, Some(4096));
+        assert_eq!(step.tables[1].reclaimable_bytes, None);
+
+        let step = StepReport::new(Operation::Reindex, None).finish(Err("boom".into()));
+        assert_eq!(step.status, StepStatus::Failed);
+        assert_eq!(step.detail.as_deref(), Some("boom"));
+
+        let step = StepReport::skipped(Operation::Cluster, None, "disabled").finish(Ok(()));
+        assert_eq!(step.status, StepStatus::Skipped);
+    }
+
+    #[test]
+    fn test_clean_report() {
+        let config = get_test_config(ConnectionEngine::Postgres, "5432");
+        let mut report = CleanReport::new(&config);
+        report.start_bytes = 1000;
+        report.end_bytes = 400;
+
+        let mut step = StepReport::new(Operation::Vacuum, None);
+        step.warn("slow");
+        report.add_step(step.finish(Ok(())));
+        report.add_step(StepReport::skipped(Operation::Cluster, None, "disabled"));
+        let report = report.finish();
+
+        assert_eq!(report.reclaimed_bytes(), 600);
+        assert_eq!(report.warning_count(), 1);
+        assert_eq!(report.count_steps(StepStatus::Skipped), 1);
+        assert!(report.is_healthy());
+
+        let mut report = CleanReport::new(&config);
+        report.start_bytes = 10;
+        report.end_bytes = 20;
+        report.add_step(StepReport::new(Operation::Analyze, None).finish(Err("boom".into())));
+        assert_eq!(report.reclaimed_bytes(), 0);
+        assert!(!report.is_healthy());
+
+        let mut report = CleanReport::new(&config);
+        report.error("connection lost");
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    fn test_locked_tables() {
+        let mut step = StepReport::new(Operation::Optimize, None);
+        step.record_statement("`app`.`users`", "OPTIMIZE TABLE `app`.`users`", Duration::ZERO, Ok(()));
+        step.record_statement("`app`.`logs`", "OPTIMIZE TABLE `app`.`logs`", Duration::ZERO, Err(sqlx::Error::RowNotFound));
+        assert_eq!(step.tables[1].status, StepStatus::Failed);
+        assert!(step.locked.is_empty());
+
+        let lock_timeout = sqlx::Error::Database(Box::new(TestDatabaseError("55P03")));
+        step.record_statement("`app`.`orders`", "OPTIMIZE TABLE `app`.`orders`", Duration::ZERO, Err(lock_timeout));
+        let mut report = CleanReport::new(&get_test_config(ConnectionEngine::Mysql, "3306"));
+        report.add_step(StepReport::skipped(Operation::Repair, None, "disabled"));
+        report.add_step(step.finish(Ok(())));
+
+        assert!(report.take_locked(2).is_empty());
+        let locked = report.take_locked(0);
+        assert_eq!(locked.len(), 1);
+        assert_eq!(locked[0].0, Operation::Optimize);
+        assert_eq!(locked[0].1[0].1, "OPTIMIZE TABLE `app`.`orders`");
+        assert!(report.take_locked(0).is_empty());
+        assert_eq!(report.steps[1].tables[2].detail.as_deref(), Some(LOCKED));
+    }
+
+    #[test]
+    fn test_clean_report_serialize() {
+        let config = get_test_config(ConnectionEngine::Mysql, "3306");
+        let mut report = CleanReport::new(&config);
+        report.add_step(StepReport::skipped(Operation::PurgeLogs, None, "disabled"));
+        let json: serde_json::Value = serde_json::to_value(report.finish()).unwrap();
+
+        assert_eq!(json["driver"], "Mysql");
+        assert_eq!(json["host"], "localhost:3306");
+        assert_eq!(json["steps"][0]["operation"], "purge_logs");
+        assert_eq!(json["steps"][0]["status"], "skipped");
+        assert_eq!(json["steps"][0]["detail"], "disabled");
+        assert!(json["steps"][0].get("removed").is_none());
+        assert!(json["steps"][0].get("would_remove").is_none());
+        assert!(json["steps"][0].get("target").is_none());
+    }
+
+    #[test]
+    fn test_dry_run_remove() {
+        let mut step = StepReport::new(Operation::PreparedTransactions, None);
+        step.dry_run_remove("roll back", String::from("prepared transaction tx1"));
+        assert!(step.removed.is_empty());
+        assert_eq!(step.would_remove, vec![String::from("prepared transaction tx1")]);
+
+        // Listing what a dry run would remove is not a warning
+        let step: StepReport = step.finish(Ok(()));
+        assert_eq!(step.status, StepStatus::Success);
+        let json: serde_json::Value = serde_json::to_value(step).unwrap();
+        assert_eq!(json["would_remove"][0], "prepared transaction tx1");
+        assert!(json.get("removed").is_none());
+    }
+}
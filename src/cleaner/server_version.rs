@@ -0,0 +1,364 @@
+use crate::config::ConnectionEngine;
+use std::fmt::{Display, Formatter};
+
+/// Flavor of a server speaking the `MySQL` protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MySqlFlavor {
+    MySql,
+    MariaDb,
+    /// Percona Server, which follows the `MySQL` releases
+    Percona,
+}
+
+impl Display for MySqlFlavor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name: &str = match self {
+            Self::MySql => "MySQL",
+            Self::MariaDb => "MariaDB",
+            Self::Percona => "Percona Server",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Flavor and version of a `MySQL` or `MariaDB` server, used to choose the statements it supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct MySqlVersion {
+    pub flavor: MySqlFlavor,
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl MySqlVersion {
+    /// Parse the result of `SELECT VERSION(), @@version_comment`
+    /// such as `8.0.36` with `MySQL Community Server - GPL`, or `10.11.6-MariaDB-0+deb12u1`
+    pub fn parse(version: &str, comment: &str) -> Self {
+        let flavor: MySqlFlavor = if version.contains("MariaDB") || comment.contains("MariaDB") {
+            MySqlFlavor::MariaDb
+        } else if version.contains("Percona") || comment.contains("Percona") {
+            MySqlFlavor::Percona
+        } else {
+            MySqlFlavor::MySql
+        };
+        // Old MariaDB clients see a 5.5.5- prefix added for compatibility
+        let number: &str = version.strip_prefix("5.5.5-").unwrap_or(version);
+        let mut parts = number
+            .split(|c: char| !c.is_ascii_digit())
+            .take(3)
+            .map(|part| part.parse::<u32>().unwrap_or(0));
+        Self {
+            flavor,
+            major: parts.next().unwrap_or(0),
+            minor: parts.next().unwrap_or(0),
+            patch: parts.next().unwrap_or(0),
+        }
+    }
+
+    /// Version assumed when the server cannot be queried, from the configured driver
+    /// No version-specific statement is used with it
+    pub const fn unknown(driver: &ConnectionEngine) -> Self {
+        let flavor: MySqlFlavor = match driver {
+            ConnectionEngine::MariaDB => MySqlFlavor::MariaDb,
+            _ => MySqlFlavor::MySql,
+        };
+        Self {
+            flavor,
+            major: 0,
+            minor: 0,
+            patch: 0,
+        }
+    }
+
+    /// Returns true if the server runs at least the given version
+    #[inline]
+    #[must_use]
+    pub const fn at_least(&self, major: u32, minor: u32, patch: u32) -> bool {
+        self.major > major
+            || (self.major == major && (self.minor > minor || (self.minor == minor && self.patch >= patch)))
+    }
+
+    /// Returns true if the version is unknown
+    #[inline]
+    #[must_use]
+    pub const fn is_unknown(&self) -> bool {
+        self.major == 0
+    }
+
+    /// Returns true if the flavor is `MariaDB`
+    #[inline]
+    #[must_use]
+    pub const fn is_mariadb(&self) -> bool {
+        matches!(self.flavor, MySqlFlavor::MariaDb)
+    }
+
+    /// Returns true if the query cache statements exist, they were removed in `MySQL` 8.0
+    #[inline]
+    #[must_use]
+    pub const fn has_query_cache(&self) -> bool {
+        self.is_mariadb() || (!self.is_unknown() && !self.at_least(8, 0, 0))
+    }
+
+    /// Name of a dynamic privilege of `MySQL` 8, spelled with a space on `MariaDB` (`CONNECTION ADMIN`)
+    #[must_use]
+    pub fn privilege(&self, name: &str) -> String {
+        if self.is_mariadb() {
+            name.replace('_', " ")
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Driver matching the flavor of the server
+    #[inline]
+    #[must_use]
+    pub const fn driver(&self) -> ConnectionEngine {
+        match self.flavor {
+            MySqlFlavor::MariaDb => ConnectionEngine::MariaDB,
+            MySqlFlavor::MySql | MySqlFlavor::Percona => ConnectionEngine::Mysql,
+        }
+    }
+
+    /// Statement describing the replication of this server, SHOW SLAVE STATUS was renamed
+    /// in `MySQL` 8.0.22 and `MariaDB` 10.5.1, and removed in `MySQL` 8.4
+    #[inline]
+    #[must_use]
+    pub const fn replica_status_statement(&self) -> &'static str {
+        let renamed: bool = if self.is_mariadb() {
+            self.at_least(10, 5, 1)
+        } else {
+            self.at_least(8, 0, 22)
+        };
+        if renamed {
+            "SHOW REPLICA STATUS"
+        } else {
+            "SHOW SLAVE STATUS"
+        }
+    }
+
+    /// Statement listing the replicas registered on this source, SHOW SLAVE HOSTS was renamed
+    /// in `MySQL` 8.0.22 and `MariaDB` 10.5.1, and removed in `MySQL` 8.4
+    #[inline]
+    #[must_use]
+    pub const fn replicas_statement(&self) -> &'static str {
+        let renamed: bool = if self.is_mariadb() {
+            self.at_least(10, 5, 1)
+        } else {
+            self.at_least(8, 0, 22)
+        };
+        match (renamed, self.is_mariadb()) {
+            (false, _) => "SHOW SLAVE HOSTS",
+            (true, true) => "SHOW REPLICA HOSTS",
+            (true, false) => "SHOW REPLICAS",
+        }
+    }
+
+    /// FLUSH statements valid on this server, in execution order
+    #[must_use]
+    pub fn flush_statements(&self) -> Vec<&'static str> {
+        let mut statements: Vec<&'static str> = vec!["FLUSH TABLES;"]; // Close all tables
+        // Reset host cache, FLUSH HOSTS is deprecated since MySQL 8.0.23 and removed in 8.4
+        if !self.is_mariadb() && self.at_least(8, 0, 23) {
+            statements.push("TRUNCATE TABLE performance_schema.host_cache;");
+        } else {
+            statements.push("FLUSH HOSTS;");
+        }
+        statements.push("FLUSH USER_RESOURCES;"); // Reset per-user resource limits
+        if self.has_query_cache() {
+            statements.push("FLUSH QUERY CACHE;"); // Defragment the query cache
+            statements.push("RESET QUERY CACHE;"); // Remove all queries from the query cache
+        }
+        statements.push("FLUSH LOGS;"); // Flush all logs
+        statements
+    }
+}
+
+impl Display for MySqlVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_unknown() {
+            return write!(f, "{} (unknown version)", self.flavor);
+        }
+        write!(f, "{} {}.{}.{}", self.flavor, self.major, self.minor, self.patch)
+    }
+}
+
+/// Version of a `PostgreSQL` server, used to choose the statements it supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub struct PgVersion {
+    /// Value of `server_version_num`, such as `160002` for 16.2, 0 when unknown
+    pub num: u32,
+}
+
+impl PgVersion {
+    /// Parse the value of `server_version_num`
+    pub fn parse(server_version_num: &str) -> Self {
+        Self {
+            num: server_version_num.trim().parse().unwrap_or(0),
+        }
+    }
+
+    /// Version assumed when the server cannot be queried
+    /// No version-specific statement is used with it
+    pub const fn unknown() -> Self {
+        Self { num: 0 }
+    }
+
+    /// Returns true if the version is unknown
+    #[inline]
+    #[must_use]
+    pub const fn is_unknown(&self) -> bool {
+        self.num == 0
+    }
+
+    /// Major version, such as 16 or 9 for 9.6 which numbered its releases with two components
+    #[inline]
+    #[must_use]
+    pub const fn major(&self) -> u32 {
+        self.num / 10000
+    }
+
+    /// Returns true if REINDEX TABLE CONCURRENTLY exists, since `PostgreSQL` 12
+    #[inline]
+    #[must_use]
+    pub const fn has_reindex_concurrently(&self) -> bool {
+        self.num >= 120_000
+    }
+
+    /// Options of a VACUUM statement on this server, in the parenthesized form
+    /// SKIP_LOCKED and INDEX_CLEANUP exist since 12, PARALLEL since 13, PROCESS_TOAST and INDEX_CLEANUP AUTO since 14
+    /// PARALLEL cannot be combined with FULL, which rebuilds the indexes anyway
+    /// SKIP_LOCKED is left out when `skip_locked` is false, so a locked table fails with a lock timeout
+    /// and is reported and retried instead of being skipped silently by the server
+    #[must_use]
+    pub fn vacuum_options(&self, full: bool, parallel_workers: Option<u32>, skip_locked: bool) -> String {
+        let mut options: Vec<String> = Vec::with_capacity(6);
+        if full {
+            options.push(String::from("FULL"));
+        }
+        options.push(String::from("ANALYZE"));
+        if skip_locked && self.num >= 120_000 {
+            options.push(String::from("SKIP_LOCKED"));
+        }
+        if !full && self.num >= 140_000 {
+            options.push(String::from("INDEX_CLEANUP AUTO"));
+            options.push(String::from("PROCESS_TOAST"));
+        }
+        if let Some(workers) = parallel_workers.filter(|_| !full && self.num >= 130_000) {
+            options.push(format!("PARALLEL {workers}"));
+        }
+        options.join(", ")
+    }
+}
+
+impl Display for PgVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.num {
+            0 => write!(f, "PostgreSQL (unknown version)"),
+            // Before 10, the first two components formed the major version
+            num if num < 100_000 => write!(f, "PostgreSQL {}.{}.{}", num / 10000, num / 100 % 100, num % 100),
+            num => write!(f, "PostgreSQL {}.{}", num / 10000, num % 10000),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mysql_version() {
+        let mysql = MySqlVersion::parse("8.0.36", "MySQL Community Server - GPL");
+        assert_eq!(mysql.flavor, MySqlFlavor::MySql);
+        assert_eq!((mysql.major, mysql.minor, mysql.patch), (8, 0, 36));
+        assert_eq!(mysql.to_string(), "MySQL 8.0.36");
+
+        let mariadb = MySqlVersion::parse("5.5.5-10.11.6-MariaDB-0+deb12u1", "Debian 12");
+        assert_eq!(mariadb.flavor, MySqlFlavor::MariaDb);
+        assert_eq!((mariadb.major, mariadb.minor, mariadb.patch), (10, 11, 6));
+        assert_eq!(mariadb.driver(), ConnectionEngine::MariaDB);
+
+        let percona = MySqlVersion::parse("8.0.35-27", "Percona Server (GPL), Release 27");
+        assert_eq!(percona.flavor, MySqlFlavor::Percona);
+        assert_eq!(percona.driver(), ConnectionEngine::Mysql);
+
+        assert!(MySqlVersion::unknown(&ConnectionEngine::Mysql).is_unknown());
+        assert!(mysql.at_least(8, 0, 23) && !mysql.at_least(8, 4, 0));
+    }
+
+    #[test]
+    fn test_flush_statements() {
+        let mysql8 = MySqlVersion::parse("8.4.0", "MySQL Community Server - GPL").flush_statements();
+        assert!(!mysql8.contains(&"FLUSH QUERY CACHE;") && !mysql8.contains(&"FLUSH HOSTS;"));
+        assert!(mysql8.contains(&"TRUNCATE TABLE performance_schema.host_cache;"));
+        // Privileges and status counters are only reset by their own opt-in steps
+        assert!(!mysql8.contains(&"FLUSH PRIVILEGES;") && !mysql8.contains(&"FLUSH STATUS;"));
+
+        let mysql57 = MySqlVersion::parse("5.7.44-log", "MySQL Community Server (GPL)").flush_statements();
+        assert!(mysql57.contains(&"RESET QUERY CACHE;") && mysql57.contains(&"FLUSH HOSTS;"));
+
+        let mariadb = MySqlVersion::parse("11.4.2-MariaDB", "mariadb.org binary distribution").flush_statements();
+        assert!(mariadb.contains(&"FLUSH QUERY CACHE;") && mariadb.contains(&"FLUSH HOSTS;"));
+
+        let percona = MySqlVersion::parse("8.0.35-27", "Percona Server (GPL), Release 27").flush_statements();
+        assert!(!percona.contains(&"RESET QUERY CACHE;"));
+
+        let unknown = MySqlVersion::unknown(&ConnectionEngine::Mysql).flush_statements();
+        assert!(!unknown.contains(&"FLUSH QUERY CACHE;"));
+    }
+
+    #[test]
+    fn test_replica_status_statement() {
+        let mysql = MySqlVersion::parse("8.0.36", "MySQL Community Server - GPL");
+        assert_eq!(mysql.replica_status_statement(), "SHOW REPLICA STATUS");
+        let mysql57 = MySqlVersion::parse("5.7.44-log", "MySQL Community Server (GPL)");
+        assert_eq!(mysql57.replica_status_statement(), "SHOW SLAVE STATUS");
+        let mariadb = MySqlVersion::parse("10.4.32-MariaDB", "mariadb.org binary distribution");
+        assert_eq!(mariadb.replica_status_statement(), "SHOW SLAVE STATUS");
+        let mariadb = MySqlVersion::parse("10.11.6-MariaDB", "mariadb.org binary distribution");
+        assert_eq!(mariadb.replica_status_statement(), "SHOW REPLICA STATUS");
+        assert_eq!(mariadb.replicas_statement(), "SHOW REPLICA HOSTS");
+        let mysql = MySqlVersion::parse("8.4.0", "MySQL Community Server - GPL");
+        assert_eq!(mysql.replicas_statement(), "SHOW REPLICAS");
+        let mysql = MySqlVersion::parse("5.7.44", "MySQL Community Server (GPL)");
+        assert_eq!(mysql.replicas_statement(), "SHOW SLAVE HOSTS");
+    }
+
+    #[test]
+    fn test_privilege_names() {
+        let mariadb = MySqlVersion::unknown(&ConnectionEngine::MariaDB);
+        assert_eq!(mariadb.privilege("BINLOG_ADMIN"), "BINLOG ADMIN");
+        let mysql = MySqlVersion::unknown(&ConnectionEngine::Mysql);
+        assert_eq!(mysql.privilege("BINLOG_ADMIN"), "BINLOG_ADMIN");
+    }
+
+    #[test]
+    fn test_pg_version() {
+        let pg16 = PgVersion::parse("160002");
+        assert_eq!(pg16.to_string(), "PostgreSQL 16.2");
+        assert_eq!(pg16.major(), 16);
+        assert!(pg16.has_reindex_concurrently());
+        assert_eq!(PgVersion::parse("90624").to_string(), "PostgreSQL 9.6.24");
+        assert!(!PgVersion::parse("110022").has_reindex_concurrently());
+        assert!(PgVersion::parse("").is_unknown());
+    }
+
+    #[test]
+    fn test_vacuum_options() {
+        let pg16 = PgVersion::parse("160002");
+        assert_eq!(
+            pg16.vacuum_options(false, Some(4), true),
+            "ANALYZE, SKIP_LOCKED, INDEX_CLEANUP AUTO, PROCESS_TOAST, PARALLEL 4"
+        );
+        assert_eq!(pg16.vacuum_options(true, Some(4), true), "FULL, ANALYZE, SKIP_LOCKED");
+        assert_eq!(pg16.vacuum_options(true, None, false), "FULL, ANALYZE");
+        assert_eq!(
+            PgVersion::parse("130010").vacuum_options(false, Some(2), true),
+            "ANALYZE, SKIP_LOCKED, PARALLEL 2"
+        );
+        assert_eq!(PgVersion::parse("110022").vacuum_options(true, None, true), "FULL, ANALYZE");
+        assert_eq!(PgVersion::unknown().vacuum_options(false, Some(2), true), "ANALYZE");
+    }
+}
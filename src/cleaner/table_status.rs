@@ -0,0 +1,223 @@
+use crate::config::FragmentationThresholds;
+use crate::quote::Dialect;
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+use std::fmt::{Display, Formatter};
+
+/// Storage engine of a `MySQL` table, which decides the maintenance it supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TableEngine {
+    /// Rebuilt once, by OPTIMIZE TABLE or ALTER TABLE ... ENGINE=InnoDB
+    InnoDb,
+    /// `MyISAM` and Aria: CHECK, REPAIR if needed, then OPTIMIZE
+    MyIsam,
+    /// ARCHIVE and CSV: only CHECK and REPAIR
+    RepairOnly,
+    /// Any other engine (MEMORY, BLACKHOLE, ...), left untouched
+    Other,
+}
+
+impl TableEngine {
+    /// Get the engine from the `ENGINE` column of `information_schema.TABLES`
+    #[must_use]
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "innodb" => Self::InnoDb,
+            "myisam" | "aria" => Self::MyIsam,
+            "archive" | "csv" => Self::RepairOnly,
+            _ => Self::Other,
+        }
+    }
+
+    /// Returns true if CHECK TABLE and REPAIR TABLE are supported
+    #[inline]
+    #[must_use]
+    pub const fn supports_repair(self) -> bool {
+        matches!(self, Self::MyIsam | Self::RepairOnly)
+    }
+
+    /// Returns true if OPTIMIZE TABLE is supported
+    #[inline]
+    #[must_use]
+    pub const fn supports_optimize(self) -> bool {
+        matches!(self, Self::InnoDb | Self::MyIsam)
+    }
+
+    /// Returns true if ANALYZE TABLE is supported
+    #[inline]
+    #[must_use]
+    pub const fn supports_analyze(self) -> bool {
+        matches!(self, Self::InnoDb | Self::MyIsam)
+    }
+}
+
+impl Display for TableEngine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name: &str = match self {
+            Self::InnoDb => "InnoDB",
+            Self::MyIsam => "MyISAM",
+            Self::RepairOnly => "ARCHIVE/CSV",
+            Self::Other => "other",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Engine and free space of a `MySQL` table, as reported by `information_schema.TABLES`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct TableStatus {
+    /// Quoted name of the table, `schema`.`table`, safe to splice into statements
+    pub table: String,
+    pub schema: String,
+    pub engine: TableEngine,
+    pub data_length: i64,
+    pub data_free: i64,
+}
+
+impl TableStatus {
+    /// Engine and free space of every base table in the given schemas, or in all non-system schemas when empty
+    /// The schema names are bound as parameters, one per placeholder
+    #[must_use]
+    pub fn table_status_sql(schemas: &[String]) -> String {
+        let filter: String = if schemas.is_empty() {
+            String::from("TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys')")
+        } else {
+            format!("TABLE_SCHEMA IN ({})", Dialect::MySql.placeholders(schemas.len()))
+        };
+        format!(
+            "SELECT TABLE_SCHEMA AS table_schema, TABLE_NAME AS table_name, \
+             COALESCE(ENGINE, '') AS engine, \
+             CAST(COALESCE(DATA_LENGTH, 0) AS SIGNED) AS data_length, \
+             CAST(COALESCE(DATA_FREE, 0) AS SIGNED) AS data_free \
+             FROM information_schema.TABLES WHERE TABLE_TYPE = 'BASE TABLE' AND {filter};"
+        )
+    }
+
+    /// Read the status of a table from a row of `table_status_sql`
+    pub fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let schema: String = row.try_get("table_schema")?;
+        let name: String = row.try_get("table_name")?;
+        let engine: String = row.try_get("engine")?;
+        Ok(Self {
+            table: Dialect::MySql.quote_qualified(&schema, &name),
+            schema,
+            engine: TableEngine::from_name(&engine),
+            data_length: row.try_get("data_length")?,
+            data_free: row.try_get("data_free")?,
+        })
+    }
+
+    /// Share of free space compared to the data of the table
+    #[must_use]
+    pub fn free_ratio(&self) -> f64 {
+        if self.data_length <= 0 {
+            return 0.0;
+        }
+        self.data_free as f64 / self.data_length as f64
+    }
+
+    /// Returns true if rebuilding the table is worth it
+    #[must_use]
+    pub fn needs_optimize(&self, thresholds: &FragmentationThresholds) -> bool {
+        self.data_free > 0
+            && (self.free_ratio() >= thresholds.min_free_ratio
+                || self.data_free >= thresholds.min_free_bytes)
+    }
+
+    /// Why OPTIMIZE TABLE must not run on this table, None if it should
+    #[must_use]
+    pub fn optimize_skip_reason(
+        &self,
+        thresholds: &FragmentationThresholds,
+        already_rebuilt: bool,
+    ) -> Option<&'static str> {
+        if self.engine == TableEngine::RepairOnly {
+            Some("ARCHIVE/CSV tables are only repaired")
+        } else if !self.engine.supports_optimize() {
+            Some("OPTIMIZE not supported by the storage engine")
+        } else if already_rebuilt {
+            Some("already rebuilt in this run")
+        } else if !self.needs_optimize(thresholds) {
+            Some("below fragmentation thresholds")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_status(engine: TableEngine, data_length: i64, data_free: i64) -> TableStatus {
+        TableStatus {
+            table: String::from("`app`.`orders`"),
+            schema: String::from("app"),
+            engine,
+            data_length,
+            data_free,
+        }
+    }
+
+    #[test]
+    fn test_needs_optimize() {
+        let thresholds = FragmentationThresholds {
+            min_free_ratio: 0.2,
+            min_free_bytes: 1000,
+        };
+        assert!(get_test_status(TableEngine::InnoDb, 100, 30).needs_optimize(&thresholds));
+        assert!(get_test_status(TableEngine::InnoDb, 100_000, 2000).needs_optimize(&thresholds));
+        assert!(!get_test_status(TableEngine::InnoDb, 100_000, 500).needs_optimize(&thresholds));
+        assert!(!get_test_status(TableEngine::InnoDb, 0, 0).needs_optimize(&thresholds));
+        assert!((get_test_status(TableEngine::InnoDb, 200, 50).free_ratio() - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_table_engine() {
+        assert_eq!(TableEngine::from_name("InnoDB"), TableEngine::InnoDb);
+        assert_eq!(TableEngine::from_name("Aria"), TableEngine::MyIsam);
+        assert_eq!(TableEngine::from_name("CSV"), TableEngine::RepairOnly);
+        assert_eq!(TableEngine::from_name("MEMORY"), TableEngine::Other);
+        assert!(TableEngine::MyIsam.supports_repair() && TableEngine::MyIsam.supports_optimize());
+        assert!(!TableEngine::InnoDb.supports_repair());
+        assert!(!TableEngine::RepairOnly.supports_optimize() && !TableEngine::RepairOnly.supports_analyze());
+    }
+
+    #[test]
+    fn test_optimize_skip_reason() {
+        let thresholds = FragmentationThresholds {
+            min_free_ratio: 0.2,
+            min_free_bytes: 1000,
+        };
+        let fragmented = get_test_status(TableEngine::InnoDb, 100, 30);
+        assert_eq!(fragmented.optimize_skip_reason(&thresholds, false), None);
+        assert_eq!(
+            fragmented.optimize_skip_reason(&thresholds, true),
+            Some("already rebuilt in this run")
+        );
+        assert_eq!(
+            get_test_status(TableEngine::MyIsam, 100, 0).optimize_skip_reason(&thresholds, false),
+            Some("below fragmentation thresholds")
+        );
+        assert_eq!(
+            get_test_status(TableEngine::RepairOnly, 100, 30).optimize_skip_reason(&thresholds, false),
+            Some("ARCHIVE/CSV tables are only repaired")
+        );
+    }
+
+    #[test]
+    fn test_table_status_sql() {
+        let sql = TableStatus::table_status_sql(&[String::from("test"), String::from("test1")]);
+        assert_eq!(
+            sql,
+            "SELECT TABLE_SCHEMA AS table_schema, TABLE_NAME AS table_name, COALESCE(ENGINE, '') AS engine, CAST(COALESCE(DATA_LENGTH, 0) AS SIGNED) AS data_length, CAST(COALESCE(DATA_FREE, 0) AS SIGNED) AS data_free FROM information_schema.TABLES WHERE TABLE_TYPE = 'BASE TABLE' AND TABLE_SCHEMA IN (?, ?);"
+        );
+
+        let sql = TableStatus::table_status_sql(&[]);
+        assert!(sql.ends_with(
+            "TABLE_SCHEMA NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys');"
+        ));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::config::CleanerConfig;
+use crate::output::OutputFormat;
+use std::fmt::Display;
+
+/// Default configuration file used when `--config` is not provided
+pub const DEFAULT_CONFIG_FILE: &str = "cleaner.json";
+
+/// Usage text printed by `--help`
+pub const USAGE: &str = "Usage: DBMSCleaner [COMMAND] [OPTIONS]
+
+Commands:
+  clean             Clean the configured databases (default)
+  plan              Show what would be executed without changing anything
+  inspect           Connect to each database and print server information
+  validate-config   Load and validate the configuration file, then exit
+  list              List the configured databases
+
+Options:
+  -c, --config <PATH>   Configuration file to load (default: cleaner.json)
+      --only <NAME>     Only process the named database (repeatable, comma separated)
+      --dry-run         Do not modify anything, only report what would be done
+  -y, --yes             Do not ask for confirmation
+  -o, --output <FORMAT> Result format of clean and plan: text, json or ndjson (default: text)
+  -h, --help            Print this help
+  -V, --version         Print the version";
+
+/// Subcommand selected on the command line
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Command {
+    Clean,
+    Plan,
+    Inspect,
+    ValidateConfig,
+    List,
+    Help,
+    Version,
+}
+
+impl Command {
+    /// Parse a subcommand from its command-line name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clean" => Some(Self::Clean),
+            "plan" => Some(Self::Plan),
+            "inspect" => Some(Self::Inspect),
+            "validate-config" => Some(Self::ValidateConfig),
+            "list" => Some(Self::List),
+            "help" => Some(Self::Help),
+            "version" => Some(Self::Version),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Clean => write!(f, "clean"),
+            Self::Plan => write!(f, "plan"),
+            Self::Inspect => write!(f, "inspect"),
+            Self::ValidateConfig => write!(f, "validate-config"),
+            Self::List => write!(f, "list"),
+            Self::Help => write!(f, "help"),
+            Self::Version => write!(f, "version"),
+        }
+    }
+}
+
+/// Parsed command-line arguments
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[must_use]
+pub struct CliArgs {
+    pub command: Command,
+    pub config_path: String,
+    pub only: Vec<String>,
+    pub dry_run: bool,
+    pub yes: bool,
+    pub output: OutputFormat,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        Self {
+            command: Command::Clean,
+            config_path: String::from(DEFAULT_CONFIG_FILE),
+            only: Vec::new(),
+            dry_run: false,
+            yes: false,
+            output: OutputFormat::Text,
+        }
+    }
+}
+
+impl CliArgs {
+    /// Parse the arguments, excluding the program name
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut cli_args = Self::default();
+        let mut command: Option<Command> = None;
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+
+            match flag.as_str() {
+                "-c" | "--config" => {
+                    cli_args.config_path = Self::flag_value(&flag, inline_value, &mut args)?;
+                }
+                "--only" => {
+                    let value = Self::flag_value(&flag, inline_value, &mut args)?;
+                    cli_args.only.extend(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|name| !name.is_empty())
+                            .map(String::from),
+                    );
+                }
+                "-o" | "--output" => {
+                    let value = Self::flag_value(&flag, inline_value, &mut args)?;
+                    cli_args.output = OutputFormat::from_name(&value)
+                        .ok_or_else(|| format!("Unknown output format: {value}"))?;
+                }
+                "--dry-run" => cli_args.dry_run = true,
+                "-y" | "--yes" => cli_args.yes = true,
+                "-h" | "--help" => command = Some(Command::Help),
+                "-V" | "--version" => command = Some(Command::Version),
+                _ if flag.starts_with('-') => return Err(format!("Unknown option: {arg}")),
+                _ => {
+                    if command.is_some() {
+                        return Err(format!("Unexpected argument: {arg}"));
+                    }
+                    command = Some(
+                        Command::from_name(&arg).ok_or_else(|| format!("Unknown command: {arg}"))?,
+                    );
+                }
+            }
+        }
+
+        cli_args.command = command.unwrap_or(Command::Clean);
+
+        if cli_args.output.is_machine_readable()
+            && !matches!(cli_args.command, Command::Clean | Command::Plan)
+        {
+            return Err(format!(
+                "--output {} is only supported by the clean and plan commands",
+                cli_args.output
+            ));
+        }
+
+        Ok(cli_args)
+    }
+
+    /// Get the value following a flag, either inline (`--flag=value`) or as the next argument
+    fn flag_value(
+        flag: &str,
+        inline_value: Option<String>,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<String, String> {
+        inline_value
+            .or_else(|| args.next())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("Missing value for {flag}"))
+    }
+
+    /// Apply the command-line overrides on top of the loaded configuration
+    pub fn apply(&self, config: &mut CleanerConfig) -> Result<(), String> {
+        if !self.only.is_empty() {
+            if let Some(unknown) = self.only.iter().find(|name| {
+                !config
+                    .databases
+                    .iter()
+                    .any(|db| db.name.as_deref() == Some(name.as_str()))
+            }) {
+                return Err(format!("No database named {unknown} in the configuration"));
+            }
+            config
+                .databases
+                .retain(|db| db.name.as_ref().is_some_and(|name| self.only.contains(name)));
+        }
+
+        if self.dry_run || self.command == Command::Plan {
+            config.dry_run = true;
+        }
+
+        if self.yes {
+            config.require_confirmation = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConnectionEngine, tests::get_test_config};
+
+    fn get_test_cleaner_config() -> CleanerConfig {
+        let mut first = get_test_config(ConnectionEngine::Mysql, "3306");
+        first.name = Some(String::from("first"));
+        let mut second = get_test_config(ConnectionEngine::Postgres, "5432");
+        second.name = Some(String::from("second"));
+
+        CleanerConfig {
+            databases: vec![first, second],
+            dry_run: false,
+            require_confirmation: true,
+            max_parallel_databases: 1,
+            maintenance_window: None,
+            max_duration: None,
+            cancel_on_deadline: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_defaults() {
+        let args = CliArgs::parse(Vec::<String>::new()).unwrap();
+        assert_eq!(args, CliArgs::default());
+        assert_eq!(args.command, Command::Clean);
+        assert_eq!(args.config_path, DEFAULT_CONFIG_FILE);
+    }
+
+    #[tokio::test]
+    async fn test_parse_command_and_flags() {
+        let args = CliArgs::parse([
+            "plan",
+            "--config",
+            "/etc/cleaner.json",
+            "--only",
+            "first, second",
+            "--only=third",
+            "--dry-run",
+            "-y",
+        ])
+        .unwrap();
+
+        assert_eq!(args.command, Command::Plan);
+        assert_eq!(args.config_path, "/etc/cleaner.json");
+        assert_eq!(args.only, vec!["first", "second", "third"]);
+        assert!(args.dry_run);
+        assert!(args.yes);
+    }
+
+    #[tokio::test]
+    async fn test_parse_all_commands() {
+        for command in [
+            Command::Clean,
+            Command::Plan,
+            Command::Inspect,
+            Command::ValidateConfig,
+            Command::List,
+        ] {
+            let args = CliArgs::parse([command.to_string()]).unwrap();
+            assert_eq!(args.command, command);
+        }
+        assert_eq!(CliArgs::parse(["--help"]).unwrap().command, Command::Help);
+        assert_eq!(CliArgs::parse(["-V"]).unwrap().command, Command::Version);
+    }
+
+    #[tokio::test]
+    async fn test_parse_errors() {
+        assert!(CliArgs::parse(["unknown"]).is_err());
+        assert!(CliArgs::parse(["--unknown"]).is_err());
+        assert!(CliArgs::parse(["--config"]).is_err());
+        assert!(CliArgs::parse(["--only="]).is_err());
+        assert!(CliArgs::parse(["clean", "plan"]).is_err());
+        assert!(CliArgs::parse(["--output", "xml"]).is_err());
+        assert!(CliArgs::parse(["list", "--output", "json"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_output() {
+        assert_eq!(CliArgs::parse(["plan", "-o", "json"]).unwrap().output, OutputFormat::Json);
+        assert_eq!(
+            CliArgs::parse(["--output=ndjson"]).unwrap().output,
+            OutputFormat::Ndjson
+        );
+        assert_eq!(CliArgs::parse(["list", "--output", "text"]).unwrap().output, OutputFormat::Text);
+    }
+
+    #[tokio::test]
+    async fn test_apply_overrides() {
+        let mut config = get_test_cleaner_config();
+        let args = CliArgs::parse(["--only", "second", "--dry-run", "--yes"]).unwrap();
+        args.apply(&mut config).unwrap();
+
+        assert_eq!(config.databases.len(), 1);
+        assert_eq!(config.databases[0].name.as_deref(), Some("second"));
+        assert!(config.dry_run);
+        assert!(!config.require_confirmation);
+    }
+
+    #[tokio::test]
+    async fn test_apply_plan_forces_dry_run() {
+        let mut config = get_test_cleaner_config();
+        CliArgs::parse(["plan"]).unwrap().apply(&mut config).unwrap();
+        assert!(config.dry_run);
+        assert_eq!(config.databases.len(), 2);
+        assert!(config.require_confirmation);
+    }
+
+    #[tokio::test]
+    async fn test_apply_unknown_database() {
+        let mut config = get_test_cleaner_config();
+        let args = CliArgs::parse(["--only", "missing"]).unwrap();
+        assert!(args.apply(&mut config).is_err());
+        assert_eq!(config.databases.len(), 2);
+    }
+}
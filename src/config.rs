@@ -1,18 +1,22 @@
 use crate::colors::{RED, RESET};
-use serde::{Deserialize, Deserializer};
+use crate::helpers::{matches_pattern, split_schema};
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Offset, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::env;
 use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufReader, Error, ErrorKind};
 use std::path::Path;
+use std::time::Duration;
 
 /// Represents the database connection engine type.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
 #[repr(u8)]
 pub enum ConnectionEngine {
     Postgres,
     Mysql,
     MariaDB,
+    #[default]
     Invalid,
 }
 
@@ -42,8 +46,472 @@ impl Display for ConnectionEngine {
     }
 }
 
+/// Represents a maintenance step that a cleaner can execute.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[repr(u8)]
+pub enum Operation {
+    TemporaryObjects,
+    PreparedTransactions,
+    ReplicationSlots,
+    Vacuum,
+    Reindex,
+    Cluster,
+    Bloat,
+    WalAndLogs,
+    Optimize,
+    Repair,
+    Analyze,
+    Flush,
+    FlushPrivileges,
+    KillConnections,
+    PurgeLogs,
+    ResetStatistics,
+    ResetQueryStatistics,
+}
+
+impl Operation {
+    /// All the known operations
+    pub const ALL: [Self; 17] = [
+        Self::TemporaryObjects,
+        Self::PreparedTransactions,
+        Self::ReplicationSlots,
+        Self::Vacuum,
+        Self::Optimize,
+        Self::Repair,
+        Self::Reindex,
+        Self::Cluster,
+        Self::Bloat,
+        Self::Analyze,
+        Self::WalAndLogs,
+        Self::Flush,
+        Self::FlushPrivileges,
+        Self::KillConnections,
+        Self::PurgeLogs,
+        Self::ResetStatistics,
+        Self::ResetQueryStatistics,
+    ];
+
+    /// Returns the name of the operation as used in the configuration file
+    #[inline]
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::TemporaryObjects => "temporary_objects",
+            Self::PreparedTransactions => "prepared_transactions",
+            Self::ReplicationSlots => "replication_slots",
+            Self::Vacuum => "vacuum",
+            Self::Reindex => "reindex",
+            Self::Cluster => "cluster",
+            Self::Bloat => "bloat",
+            Self::WalAndLogs => "wal_and_logs",
+            Self::Optimize => "optimize",
+            Self::Repair => "repair",
+            Self::Analyze => "analyze",
+            Self::Flush => "flush",
+            Self::FlushPrivileges => "flush_privileges",
+            Self::KillConnections => "kill_connections",
+            Self::PurgeLogs => "purge_logs",
+            Self::ResetStatistics => "reset_statistics",
+            Self::ResetQueryStatistics => "reset_query_statistics",
+        }
+    }
+
+    /// Parse an operation from its configuration name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name: String = name.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|operation| operation.as_str() == name)
+    }
+
+    /// Returns true if the operation is implemented by the cleaner of the given engine
+    #[must_use]
+    pub const fn supports(&self, driver: &ConnectionEngine) -> bool {
+        match driver {
+            ConnectionEngine::Postgres => matches!(
+                self,
+                Self::TemporaryObjects
+                    | Self::PreparedTransactions
+                    | Self::ReplicationSlots
+                    | Self::Vacuum
+                    | Self::Reindex
+                    | Self::Cluster
+                    | Self::Bloat
+                    | Self::WalAndLogs
+                    | Self::Analyze
+                    | Self::KillConnections
+                    | Self::ResetStatistics
+                    | Self::ResetQueryStatistics
+            ),
+            ConnectionEngine::Mysql | ConnectionEngine::MariaDB => matches!(
+                self,
+                Self::TemporaryObjects
+                    | Self::Optimize
+                    | Self::Repair
+                    | Self::Reindex
+                    | Self::Analyze
+                    | Self::Flush
+                    | Self::FlushPrivileges
+                    | Self::KillConnections
+                    | Self::PurgeLogs
+                    | Self::ResetStatistics
+                    | Self::ResetQueryStatistics
+            ),
+            ConnectionEngine::Invalid => false,
+        }
+    }
+
+    /// Returns true if the operation only runs when listed in `operations`
+    /// These steps discard server-wide state other systems may rely on, such as replication slots used by
+    /// CDC pipelines or the statistics used by monitoring
+    #[inline]
+    #[must_use]
+    pub const fn is_opt_in(&self) -> bool {
+        matches!(
+            self,
+            Self::PreparedTransactions
+                | Self::ReplicationSlots
+                | Self::FlushPrivileges
+                | Self::KillConnections
+                | Self::ResetStatistics
+                | Self::ResetQueryStatistics
+        )
+    }
+
+    /// Server-wide state discarded by an opt-in operation on the given engine, printed before cleaning
+    #[must_use]
+    pub const fn discarded_state(&self, driver: &ConnectionEngine) -> Option<&'static str> {
+        let postgres: bool = matches!(driver, ConnectionEngine::Postgres);
+        match self {
+            Self::PreparedTransactions => Some("prepared transactions older than min_age are rolled back"),
+            Self::ReplicationSlots => Some("inactive logical replication slots are dropped"),
+            Self::FlushPrivileges => Some("the privileges are reloaded from the grant tables (FLUSH PRIVILEGES)"),
+            Self::KillConnections if postgres => Some("sessions idle in transaction are terminated"),
+            Self::KillConnections => Some("sleeping client connections are killed"),
+            Self::ResetStatistics if postgres => Some("the cumulative statistics of each database (pg_stat_reset)"),
+            Self::ResetStatistics => {
+                Some("the status counters (FLUSH STATUS) and the performance_schema wait and file summaries")
+            }
+            Self::ResetQueryStatistics if postgres => Some("the query statistics of pg_stat_statements"),
+            Self::ResetQueryStatistics => Some("the statement digests of performance_schema"),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Operation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = String::deserialize(deserializer)?;
+        Self::from_name(&s).ok_or_else(|| serde::de::Error::custom(format!("unknown operation: {s}")))
+    }
+}
+
+impl Serialize for Operation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Daily time range during which the databases may be cleaned
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MaintenanceWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Offset of the window times, the local timezone of the server when not set
+    pub offset: Option<FixedOffset>,
+}
+
+impl MaintenanceWindow {
+    /// Time left before the end of the window, or None when `now` is outside of it
+    /// A window ending before it starts (e.g. 22:00 to 06:00) spans midnight
+    #[must_use]
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<Duration> {
+        let offset: FixedOffset = self
+            .offset
+            .unwrap_or_else(|| now.with_timezone(&Local).offset().fix());
+        let time: NaiveTime = now.with_timezone(&offset).time();
+
+        let inside: bool = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        };
+        if !inside {
+            return None;
+        }
+
+        let mut remaining: chrono::Duration = self.end - time;
+        if remaining < chrono::Duration::zero() {
+            remaining += chrono::Duration::days(1);
+        }
+        remaining.to_std().ok()
+    }
+
+    /// Parse a timezone: `local`, `UTC` or a fixed offset such as `+02:00`
+    fn parse_timezone(timezone: &str) -> Option<Option<FixedOffset>> {
+        match timezone.trim().to_lowercase().as_str() {
+            "local" => Some(None),
+            "utc" | "z" => Some(FixedOffset::east_opt(0)),
+            offset => offset.parse::<FixedOffset>().ok().map(Some),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MaintenanceWindow {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RawWindow {
+            start: String,
+            end: String,
+            #[serde(default)]
+            timezone: Option<String>,
+        }
+
+        let raw: RawWindow = RawWindow::deserialize(deserializer)?;
+        let parse_time = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|_| {
+                serde::de::Error::custom(format!("invalid maintenance window time: {value}, expected HH:MM"))
+            })
+        };
+        let offset: Option<FixedOffset> = match raw.timezone.as_deref() {
+            Some(timezone) => Self::parse_timezone(timezone).ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "unsupported timezone: {timezone}, use local, UTC or an offset like +02:00"
+                ))
+            })?,
+            None => None,
+        };
+
+        Ok(Self {
+            start: parse_time(&raw.start)?,
+            end: parse_time(&raw.end)?,
+            offset,
+        })
+    }
+}
+
+/// Thresholds deciding which `PostgreSQL` tables are vacuumed, and which ones are rewritten
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct BloatThresholds {
+    /// Minimum share of dead tuples for a plain VACUUM
+    pub min_dead_tuple_ratio: f64,
+    /// Minimum share of wasted space for a VACUUM FULL
+    pub min_bloat_ratio: f64,
+    /// Minimum number of wasted bytes for a VACUUM FULL
+    pub min_wasted_bytes: i64,
+}
+
+impl Default for BloatThresholds {
+    fn default() -> Self {
+        Self {
+            min_dead_tuple_ratio: 0.2,
+            min_bloat_ratio: 0.3,
+            min_wasted_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// Thresholds deciding which `MySQL` tables are rebuilt by OPTIMIZE TABLE
+/// A table is optimized when either threshold is reached
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct FragmentationThresholds {
+    /// Minimum share of free space compared to the data length
+    pub min_free_ratio: f64,
+    /// Minimum number of free bytes
+    pub min_free_bytes: i64,
+}
+
+impl Default for FragmentationThresholds {
+    fn default() -> Self {
+        Self {
+            min_free_ratio: 0.2,
+            min_free_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// Variants of the `PostgreSQL` maintenance statements, chosen from the server version by default
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct PostgresSettings {
+    /// Deprecated alias of `online_only`, folded into it by `CleanerConfig::resolve`
+    pub force_online: bool,
+    /// Parallel workers of VACUUM on `PostgreSQL` 13 and later, chosen by the server when not set
+    pub vacuum_parallel_workers: Option<u32>,
+}
+
+/// Include and exclude patterns of names, where `*` matches any characters and `?` a single one
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct NameFilter {
+    /// Names to select, every name when empty
+    pub include: Vec<String>,
+    /// Names to never select, applied after `include`
+    pub exclude: Vec<String>,
+}
+
+impl NameFilter {
+    /// Returns true if the name is selected by the patterns
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        let included: bool =
+            self.include.is_empty() || self.include.iter().any(|pattern| matches_pattern(pattern, name));
+        included && !self.exclude.iter().any(|pattern| matches_pattern(pattern, name))
+    }
+}
+
+/// Prepared transactions rolled back by the opt-in `prepared_transactions` step
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct PreparedTransactionSettings {
+    /// Minimum age of a prepared transaction, 1 hour when not set
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub min_age: Option<Duration>,
+    /// Patterns of the global transaction identifiers
+    #[serde(flatten)]
+    pub gids: NameFilter,
+}
+
+/// Inactive logical replication slots dropped by the opt-in `replication_slots` step
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ReplicationSlotSettings {
+    /// Minimum time the slot has been inactive, only known on `PostgreSQL` 17 and later
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub min_inactive_age: Option<Duration>,
+    /// Minimum number of WAL bytes retained by the slot
+    pub min_retained_bytes: i64,
+    /// Patterns of the slot names
+    #[serde(flatten)]
+    pub names: NameFilter,
+}
+
+/// Idle client connections killed by the opt-in `kill_connections` step
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct KillConnectionSettings {
+    /// Minimum time the connection has been idle, 1 hour when not set
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub min_idle: Option<Duration>,
+    /// Patterns of the connection user names
+    pub users: NameFilter,
+    /// Patterns of the client hosts, without the port
+    pub hosts: NameFilter,
+    /// Patterns of the current database of the connection
+    pub databases: NameFilter,
+    /// Only report the connections that would be killed
+    pub report_only: bool,
+}
+
+/// Binary logs purged by the `purge_logs` step
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct BinlogSettings {
+    /// Age of the oldest binary log kept, 7 days when not set
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub retention: Option<Duration>,
+    /// Skip the purge unless every replica is connected and has read all the binary logs
+    pub check_replicas: bool,
+    /// Number of replicas that must be connected, by default the replicas registered on the server
+    pub expected_replicas: Option<usize>,
+}
+
+impl Default for BinlogSettings {
+    fn default() -> Self {
+        Self {
+            retention: None,
+            check_replicas: true,
+            expected_replicas: None,
+        }
+    }
+}
+
+/// Settings of the connection pool opened for a database
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct PoolSettings {
+    /// Maximum number of connections, by default enough for `table_concurrency`
+    pub max_connections: Option<u32>,
+    /// Maximum time waiting for a connection, 30 seconds by default
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub acquire_timeout: Option<Duration>,
+    /// Maximum time a single statement may run before the server aborts it
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub statement_timeout: Option<Duration>,
+    /// Maximum time a statement waits for a table lock, the table is then skipped as locked
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub lock_timeout: Option<Duration>,
+    /// Name of the application reported to `PostgreSQL` in `pg_stat_activity`
+    pub application_name: String,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            acquire_timeout: None,
+            statement_timeout: None,
+            lock_timeout: None,
+            application_name: String::from("DBMSCleaner"),
+        }
+    }
+}
+
+/// Parse a duration such as `90m`, `2h` or `3600s`, a number without unit is in seconds
+#[must_use]
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value: &str = value.trim();
+    let (number, multiplier) = match value.char_indices().last()? {
+        (i, 's') => (&value[..i], 1),
+        (i, 'm') => (&value[..i], 60),
+        (i, 'h') => (&value[..i], 3600),
+        _ => (value, 1),
+    };
+    let number: u64 = number.trim().parse().ok()?;
+    Some(Duration::from_secs(number.checked_mul(multiplier)?))
+}
+
+/// Deserialize an optional duration written as a string (`"90m"`) or a number of seconds
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawDuration {
+        Seconds(u64),
+        Text(String),
+    }
+
+    match Option::<RawDuration>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(RawDuration::Seconds(seconds)) => Ok(Some(Duration::from_secs(seconds))),
+        Some(RawDuration::Text(text)) => parse_duration(&text)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid duration: {text}"))),
+    }
+}
+
 /// Represents the configuration for a single database connection.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 #[must_use]
 pub struct DatabaseConfig {
     pub name: Option<String>,
@@ -55,7 +523,69 @@ pub struct DatabaseConfig {
     pub password: Option<String>,
     #[serde(default)]
     pub password_env: Option<String>,
+    /// `MySQL` schemas, or `PostgreSQL` databases when `databases` is not set
+    #[serde(default)]
     pub schema: String,
+    /// `PostgreSQL` databases to clean, `*` for every database of the server
+    #[serde(default)]
+    pub databases: Option<Vec<String>>,
+    /// `PostgreSQL` schemas to clean in each database, every non-system schema when not set or `*`
+    #[serde(default)]
+    pub schemas: Option<Vec<String>>,
+    /// Operations to run, all supported operations are run when not set
+    #[serde(default)]
+    pub operations: Option<Vec<Operation>>,
+    /// Operations to never run, applied after `operations`
+    #[serde(default)]
+    pub skip_operations: Vec<Operation>,
+    /// Only print the statements that would be executed
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Number of tables processed at the same time by a step
+    #[serde(default = "default_table_concurrency")]
+    pub table_concurrency: usize,
+    /// Time range outside of which no new table operation is started
+    #[serde(default)]
+    pub maintenance_window: Option<MaintenanceWindow>,
+    /// Maximum time spent cleaning this database
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_duration: Option<Duration>,
+    /// Cancel the running statements when the deadline is reached
+    #[serde(default)]
+    pub cancel_on_deadline: bool,
+    /// Never take locks blocking the application: lock-free statements are used instead, or the step is skipped
+    #[serde(default)]
+    pub online_only: bool,
+    /// Retry the tables skipped because they were locked once every step is done
+    #[serde(default)]
+    pub retry_locked: bool,
+    /// Run the steps even when the server is a read replica or a standby
+    #[serde(default)]
+    pub allow_on_replica: bool,
+    /// Thresholds of the `PostgreSQL` bloat estimator
+    #[serde(default)]
+    pub bloat: BloatThresholds,
+    /// Thresholds of the `MySQL` fragmentation filter
+    #[serde(default)]
+    pub fragmentation: FragmentationThresholds,
+    /// Settings of the connection pools
+    #[serde(default)]
+    pub pool: PoolSettings,
+    /// Variants of the `PostgreSQL` maintenance statements
+    #[serde(default)]
+    pub postgres: PostgresSettings,
+    /// Prepared transactions rolled back by the `prepared_transactions` step
+    #[serde(default)]
+    pub prepared_transactions: PreparedTransactionSettings,
+    /// Replication slots dropped by the `replication_slots` step
+    #[serde(default)]
+    pub replication_slots: ReplicationSlotSettings,
+    /// Idle connections killed by the `kill_connections` step
+    #[serde(default)]
+    pub kill_connections: KillConnectionSettings,
+    /// Binary logs purged by the `purge_logs` step
+    #[serde(default)]
+    pub binlog: BinlogSettings,
 }
 
 /// Main configuration structure supporting multiple databases
@@ -67,12 +597,32 @@ pub struct CleanerConfig {
     pub dry_run: bool,
     #[serde(default = "default_require_confirmation")]
     pub require_confirmation: bool,
+    /// Number of databases cleaned at the same time
+    #[serde(default = "default_max_parallel_databases")]
+    pub max_parallel_databases: usize,
+    /// Default maintenance window of the databases
+    #[serde(default)]
+    pub maintenance_window: Option<MaintenanceWindow>,
+    /// Default maximum time spent cleaning each database
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_duration: Option<Duration>,
+    /// Cancel the running statements of every database when its deadline is reached
+    #[serde(default)]
+    pub cancel_on_deadline: bool,
 }
 
 fn default_require_confirmation() -> bool {
     true
 }
 
+fn default_max_parallel_databases() -> usize {
+    1
+}
+
+fn default_table_concurrency() -> usize {
+    1
+}
+
 /// Legacy Config type alias for compatibility with cleaners
 pub type Config = DatabaseConfig;
 
@@ -106,7 +656,27 @@ impl DatabaseConfig {
             (self.port.parse::<i32>().is_err(), "Port must be a number"),
             (self.host.is_empty(), "Host must not be empty"),
             (self.username.is_empty(), "Username must not be empty"),
-            (self.schema.is_empty(), "Schema must not be empty"),
+            (
+                self.schema.is_empty() && (self.driver != ConnectionEngine::Postgres || self.databases.is_none()),
+                "Schema must not be empty",
+            ),
+            (
+                self.driver != ConnectionEngine::Postgres && (self.databases.is_some() || self.schemas.is_some()),
+                "databases and schemas are only supported by postgres",
+            ),
+            (self.databases.as_ref().is_some_and(Vec::is_empty), "databases must not be empty"),
+            (self.schemas.as_ref().is_some_and(Vec::is_empty), "schemas must not be empty"),
+            (self.pool.max_connections == Some(0), "pool.max_connections must be at least 1"),
+            // max_execution_time, the only statement limit of MySQL, ignores OPTIMIZE, ALTER, CHECK and REPAIR
+            (
+                self.driver == ConnectionEngine::Mysql && self.pool.statement_timeout.is_some(),
+                "pool.statement_timeout is not supported by mysql, use max_duration with cancel_on_deadline",
+            ),
+            (
+                self.cancels_on_deadline()
+                    && self.pool.max_connections.is_some_and(|max| max as usize <= self.table_concurrency),
+                "pool.max_connections must be above table_concurrency to cancel statements on the deadline",
+            ),
             (self.driver == ConnectionEngine::Invalid, "Invalid driver"),
         ];
 
@@ -119,11 +689,98 @@ impl DatabaseConfig {
             }
         }
 
+        // Validate that the selected operations exist for this driver
+        let selected = self.operations.iter().flatten().chain(&self.skip_operations);
+        for operation in selected {
+            if !operation.supports(&self.driver) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "{RED}Operation {operation} is not supported by {}{RESET}",
+                        self.driver
+                    ),
+                ));
+            }
+        }
+
         // Validate password is available
         self.get_password()?;
 
         Ok(())
     }
+
+    /// Returns true if running statements are cancelled when a deadline is reached
+    #[must_use]
+    pub const fn cancels_on_deadline(&self) -> bool {
+        self.cancel_on_deadline && (self.max_duration.is_some() || self.maintenance_window.is_some())
+    }
+
+    /// `PostgreSQL` databases to clean, from `databases` or else from `schema`
+    /// None when every database of the server must be cleaned
+    #[must_use]
+    pub fn postgres_databases(&self) -> Option<Vec<String>> {
+        let databases: Vec<String> = match &self.databases {
+            Some(databases) => databases.iter().flat_map(|database| split_schema(database)).collect(),
+            None => split_schema(&self.schema),
+        };
+        if databases.iter().any(|database| database == "*") {
+            return None;
+        }
+        Some(databases)
+    }
+
+    /// `PostgreSQL` schemas to clean in each database, None for every non-system schema
+    #[must_use]
+    pub fn postgres_schemas(&self) -> Option<Vec<String>> {
+        let schemas: Vec<String> = self
+            .schemas
+            .iter()
+            .flatten()
+            .flat_map(|schema| split_schema(schema))
+            .collect();
+        if schemas.is_empty() || schemas.iter().any(|schema| schema == "*") {
+            return None;
+        }
+        Some(schemas)
+    }
+
+    /// Databases or schemas targeted by this configuration, for display
+    #[must_use]
+    pub fn target(&self) -> String {
+        match (&self.databases, &self.schemas) {
+            (None, None) => self.schema.clone(),
+            (databases, schemas) => {
+                let databases: String = databases.as_ref().map_or_else(|| self.schema.clone(), |d| d.join(","));
+                let schemas: String = schemas.as_ref().map_or_else(|| String::from("*"), |s| s.join(","));
+                format!("{databases} (schemas: {schemas})")
+            }
+        }
+    }
+
+    /// Server-wide state discarded by the enabled opt-in operations, as `operation: description`
+    #[must_use]
+    pub fn discarded_state(&self) -> Vec<String> {
+        Operation::ALL
+            .into_iter()
+            .filter(|operation| self.is_operation_enabled(*operation))
+            .filter_map(|operation| {
+                operation
+                    .discarded_state(&self.driver)
+                    .map(|state| format!("{operation}: {state}"))
+            })
+            .collect()
+    }
+
+    /// Check if an operation should be executed for this database
+    /// Opt-in operations only run when listed in `operations`
+    #[must_use]
+    pub fn is_operation_enabled(&self, operation: Operation) -> bool {
+        let allowed = self
+            .operations
+            .as_ref()
+            .map_or(!operation.is_opt_in(), |operations| operations.contains(&operation));
+        allowed && !self.skip_operations.contains(&operation) && operation.supports(&self.driver)
+    }
 }
 
 impl CleanerConfig {
@@ -154,11 +811,19 @@ impl CleanerConfig {
             ));
         }
 
+        if self.max_parallel_databases == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{RED}max_parallel_databases must be at least 1{RESET}"),
+            ));
+        }
+
         for (i, db_config) in self.databases.iter().enumerate() {
             let default_name = format!("Database #{}", i + 1);
             let db_name = db_config.name.as_ref().unwrap_or(&default_name);
 
-            if let Err(e) = db_config.validate() {
+            // The defaults of the cleaner configuration take part in the validation
+            if let Err(e) = self.resolve(db_config).validate() {
                 return Err(Error::new(
                     ErrorKind::InvalidInput,
                     format!("{RED}Invalid config for {db_name}: {e}{RESET}"),
@@ -168,6 +833,17 @@ impl CleanerConfig {
 
         Ok(())
     }
+
+    /// Apply the global settings to a database configuration, the database settings take precedence
+    pub fn resolve(&self, db_config: &DatabaseConfig) -> DatabaseConfig {
+        let mut db_config: DatabaseConfig = db_config.clone();
+        db_config.dry_run |= self.dry_run;
+        db_config.maintenance_window = db_config.maintenance_window.or(self.maintenance_window);
+        db_config.max_duration = db_config.max_duration.or(self.max_duration);
+        db_config.cancel_on_deadline |= self.cancel_on_deadline;
+        db_config.online_only |= db_config.postgres.force_online;
+        db_config
+    }
 }
 
 #[cfg(test)]
@@ -210,6 +886,7 @@ pub(crate) mod tests {
             password: Some("pass".to_string()),
             password_env: None,
             schema: "public".to_string(),
+            ..Default::default()
         };
         assert!(config.validate().is_ok());
     }
@@ -225,6 +902,7 @@ pub(crate) mod tests {
             password: Some("pass".to_string()),
             password_env: None,
             schema: "public".to_string(),
+            ..Default::default()
         };
         assert!(config.validate().is_err());
     }
@@ -240,6 +918,7 @@ pub(crate) mod tests {
             password: Some("pass".to_string()),
             password_env: None,
             schema: "public".to_string(),
+            ..Default::default()
         };
         assert!(config.validate().is_err());
     }
@@ -256,6 +935,7 @@ pub(crate) mod tests {
         assert_eq!(loaded_config.databases[0].driver, ConnectionEngine::Mysql);
         assert_eq!(loaded_config.databases[0].host, "localhost");
         assert_eq!(loaded_config.databases[0].port, "3306");
+        assert_eq!(loaded_config.max_parallel_databases, 1);
 
         delete_test_file_config(CONFIG_TEST_FILE);
     }
@@ -283,6 +963,7 @@ pub(crate) mod tests {
                 password: Some(String::from("password")),
                 password_env: None,
                 schema: String::from("test"),
+                ..Default::default()
             }
             .validate()
             .is_ok()
@@ -298,6 +979,7 @@ pub(crate) mod tests {
                 password: Some(String::from("password")),
                 password_env: None,
                 schema: String::from("test"),
+                ..Default::default()
             }
             .validate()
             .is_ok()
@@ -313,12 +995,146 @@ pub(crate) mod tests {
                 password: Some(String::from("password")),
                 password_env: None,
                 schema: String::from("test"),
+                ..Default::default()
             }
             .validate()
             .is_err()
         );
     }
 
+    #[tokio::test]
+    async fn test_operation_from_name() {
+        for operation in Operation::ALL {
+            assert_eq!(Operation::from_name(operation.as_str()), Some(operation));
+            assert_eq!(format!("{operation}"), operation.as_str());
+        }
+        assert_eq!(Operation::from_name(" VACUUM "), Some(Operation::Vacuum));
+        assert_eq!(Operation::from_name("unknown"), None);
+
+        let operation: Operation = serde_json::from_str("\"purge_logs\"").unwrap();
+        assert_eq!(operation, Operation::PurgeLogs);
+        assert!(serde_json::from_str::<Operation>("\"defrag\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_is_operation_enabled() {
+        let mut config: DatabaseConfig = get_test_config(ConnectionEngine::Postgres, "5432");
+        assert!(config.is_operation_enabled(Operation::Vacuum));
+        assert!(config.is_operation_enabled(Operation::Cluster));
+        assert!(!config.is_operation_enabled(Operation::Optimize));
+
+        config.operations = Some(vec![Operation::Vacuum, Operation::Analyze]);
+        assert!(config.is_operation_enabled(Operation::Vacuum));
+        assert!(config.is_operation_enabled(Operation::Analyze));
+        assert!(!config.is_operation_enabled(Operation::Cluster));
+
+        config.skip_operations = vec![Operation::Analyze];
+        assert!(config.is_operation_enabled(Operation::Vacuum));
+        assert!(!config.is_operation_enabled(Operation::Analyze));
+
+        config.operations = None;
+        assert!(config.is_operation_enabled(Operation::Cluster));
+        assert!(!config.is_operation_enabled(Operation::Analyze));
+
+        // Opt-in operations must be listed explicitly
+        assert!(!config.is_operation_enabled(Operation::PreparedTransactions));
+        assert!(!config.is_operation_enabled(Operation::ReplicationSlots));
+        config.operations = Some(vec![Operation::ReplicationSlots]);
+        assert!(config.is_operation_enabled(Operation::ReplicationSlots));
+    }
+
+    #[tokio::test]
+    async fn test_discarded_state() {
+        let mut config: DatabaseConfig = get_test_config(ConnectionEngine::Mysql, "3306");
+        assert!(config.discarded_state().is_empty());
+        assert!(!config.is_operation_enabled(Operation::FlushPrivileges));
+
+        config.operations = Some(vec![Operation::Flush, Operation::ResetStatistics, Operation::KillConnections]);
+        assert_eq!(
+            config.discarded_state(),
+            vec![
+                "kill_connections: sleeping client connections are killed",
+                "reset_statistics: the status counters (FLUSH STATUS) and the performance_schema wait and file summaries",
+            ]
+        );
+
+        config.driver = ConnectionEngine::Postgres;
+        config.operations = Some(vec![Operation::ResetQueryStatistics]);
+        assert_eq!(
+            config.discarded_state(),
+            vec!["reset_query_statistics: the query statistics of pg_stat_statements"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_name_filter() {
+        let filter: NameFilter = serde_json::from_str(r#"{"include": ["tmp_*", "cdc_?"], "exclude": ["tmp_keep*"]}"#).unwrap();
+        assert!(filter.matches("tmp_export"));
+        assert!(filter.matches("cdc_1"));
+        assert!(!filter.matches("cdc_10"));
+        assert!(!filter.matches("tmp_keep_me"));
+        assert!(!filter.matches("debezium"));
+        assert!(NameFilter::default().matches("debezium"));
+
+        let json = r#"{"min_inactive_age": "168h", "min_retained_bytes": 1024, "exclude": ["debezium*"]}"#;
+        let slots: ReplicationSlotSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(slots.min_inactive_age, Some(Duration::from_secs(168 * 3600)));
+        assert_eq!(slots.min_retained_bytes, 1024);
+        assert!(!slots.names.matches("debezium_orders"));
+
+        let json = r#"{"min_idle": "30m", "users": {"exclude": ["pgbouncer"]}, "report_only": true}"#;
+        let kill: KillConnectionSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(kill.min_idle, Some(Duration::from_secs(30 * 60)));
+        assert!(!kill.users.matches("pgbouncer"));
+        assert!(kill.hosts.matches("10.0.0.5"));
+        assert!(kill.report_only);
+
+        let binlog: BinlogSettings = serde_json::from_str(r#"{"retention": "72h", "expected_replicas": 2}"#).unwrap();
+        assert_eq!(binlog.retention, Some(Duration::from_secs(72 * 3600)));
+        assert!(binlog.check_replicas);
+        assert_eq!(binlog.expected_replicas, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_validate_unsupported_operation() {
+        let mut config: DatabaseConfig = get_test_config(ConnectionEngine::Mysql, "3306");
+        config.operations = Some(vec![Operation::Optimize, Operation::Analyze]);
+        assert!(config.validate().is_ok());
+
+        config.operations = Some(vec![Operation::Vacuum]);
+        assert!(config.validate().is_err());
+
+        config.operations = None;
+        config.skip_operations = vec![Operation::Cluster];
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deserialize_operations() {
+        let data: &str = r#"{
+            "driver": "postgres",
+            "host": "localhost",
+            "port": "5432",
+            "username": "postgres",
+            "password": "password",
+            "schema": "public",
+            "operations": ["vacuum", "analyze"],
+            "skip_operations": ["analyze"]
+        }"#;
+        let config: DatabaseConfig = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            config.operations,
+            Some(vec![Operation::Vacuum, Operation::Analyze])
+        );
+        assert_eq!(config.skip_operations, vec![Operation::Analyze]);
+        assert_eq!(config.table_concurrency, 1);
+        assert_eq!(config.bloat, BloatThresholds::default());
+        assert_eq!(config.fragmentation, FragmentationThresholds::default());
+
+        let invalid: String = data.replace("\"analyze\"]", "\"unknown\"]");
+        assert!(serde_json::from_str::<DatabaseConfig>(&invalid).is_err());
+    }
+
     fn generate_test_file_config(file_name: &str) {
         let mut file: File = File::create(file_name).unwrap();
         let data: &str = r#"{
@@ -356,6 +1172,224 @@ pub(crate) mod tests {
             password: Some(String::from("password")),
             password_env: None,
             schema: String::from("test"),
+            ..Default::default()
         }
     }
+
+    #[tokio::test]
+    async fn test_postgres_databases_and_schemas() {
+        let json = r#"{"driver": "postgres", "host": "localhost", "port": "5432", "username": "root",
+                       "password": "pw", "databases": ["app"], "schemas": ["billing", "audit"]}"#;
+        let config: DatabaseConfig = serde_json::from_str(json).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.postgres_databases(), Some(vec![String::from("app")]));
+        assert_eq!(
+            config.postgres_schemas(),
+            Some(vec![String::from("billing"), String::from("audit")])
+        );
+        assert_eq!(config.target(), "app (schemas: billing,audit)");
+
+        // The legacy schema field lists the databases, and every non-system schema is cleaned
+        let legacy = get_test_config(ConnectionEngine::Postgres, "5432");
+        assert_eq!(legacy.postgres_databases(), Some(vec![String::from("test")]));
+        assert_eq!(legacy.postgres_schemas(), None);
+        assert_eq!(legacy.target(), "test");
+
+        let all = DatabaseConfig {
+            databases: Some(vec![String::from("*")]),
+            schemas: Some(vec![String::from("*")]),
+            ..get_test_config(ConnectionEngine::Postgres, "5432")
+        };
+        assert_eq!(all.postgres_databases(), None);
+        assert_eq!(all.postgres_schemas(), None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_databases_and_schemas() {
+        let mut config = DatabaseConfig {
+            schema: String::new(),
+            databases: Some(vec![String::from("app")]),
+            ..get_test_config(ConnectionEngine::Postgres, "5432")
+        };
+        assert!(config.validate().is_ok());
+
+        config.databases = Some(Vec::new());
+        assert!(config.validate().is_err());
+
+        config.databases = None;
+        assert!(config.validate().is_err());
+
+        let mysql = DatabaseConfig {
+            schemas: Some(vec![String::from("app")]),
+            ..get_test_config(ConnectionEngine::Mysql, "3306")
+        };
+        assert!(mysql.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pool_settings() {
+        let json = r#"{"max_connections": 4, "acquire_timeout": "10s", "statement_timeout": 600, "lock_timeout": "30s"}"#;
+        let pool: PoolSettings = serde_json::from_str(json).unwrap();
+        assert_eq!(pool.lock_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(pool.max_connections, Some(4));
+        assert_eq!(pool.acquire_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(pool.statement_timeout, Some(Duration::from_secs(600)));
+        assert_eq!(pool.application_name, "DBMSCleaner");
+
+        let mut config = get_test_config(ConnectionEngine::Postgres, "5432");
+        config.pool.max_connections = Some(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_postgres_settings() {
+        let json = r#"{"driver": "postgres", "host": "localhost", "port": "5432", "username": "root",
+                       "password": "pw", "schema": "app", "postgres": {"force_online": true, "vacuum_parallel_workers": 4}}"#;
+        let config: DatabaseConfig = serde_json::from_str(json).unwrap();
+        assert!(config.postgres.force_online);
+        assert_eq!(config.postgres.vacuum_parallel_workers, Some(4));
+        assert_eq!(get_test_config(ConnectionEngine::Postgres, "5432").postgres, PostgresSettings::default());
+    }
+
+    #[tokio::test]
+    async fn test_validate_max_parallel_databases() {
+        let mut config = CleanerConfig {
+            databases: vec![get_test_config(ConnectionEngine::Postgres, "5432")],
+            dry_run: false,
+            require_confirmation: true,
+            max_parallel_databases: 4,
+            maintenance_window: None,
+            max_duration: None,
+            cancel_on_deadline: false,
+        };
+        assert!(config.validate().is_ok());
+
+        config.max_parallel_databases = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_cancel_connections() {
+        let mut db_config: DatabaseConfig = get_test_config(ConnectionEngine::Mysql, "3306");
+        db_config.table_concurrency = 4;
+        db_config.pool.max_connections = Some(4);
+        let mut config = CleanerConfig {
+            databases: vec![db_config],
+            dry_run: false,
+            require_confirmation: true,
+            max_parallel_databases: 1,
+            maintenance_window: None,
+            max_duration: Some(Duration::from_secs(3600)),
+            cancel_on_deadline: false,
+        };
+        assert!(config.validate().is_ok());
+
+        // The deadline set on the cleaner configuration needs a free connection to cancel the statements
+        config.cancel_on_deadline = true;
+        assert!(config.validate().is_err());
+        config.databases[0].pool.max_connections = Some(5);
+        assert!(config.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_statement_timeout() {
+        let mut config: DatabaseConfig = get_test_config(ConnectionEngine::Mysql, "3306");
+        config.pool.statement_timeout = Some(Duration::from_secs(60));
+        assert!(config.validate().is_err());
+        config.driver = ConnectionEngine::MariaDB;
+        assert!(config.validate().is_ok());
+        config.driver = ConnectionEngine::Postgres;
+        assert!(config.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_parse_duration() {
+        assert_eq!(parse_duration("90m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("45s"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_duration(" 600 "), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("1d"), None);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_window_remaining() {
+        let window: MaintenanceWindow = serde_json::from_str(
+            r#"{"start": "22:00", "end": "06:00", "timezone": "UTC"}"#,
+        )
+        .unwrap();
+        let at = |time: &str| {
+            DateTime::parse_from_rfc3339(&format!("2024-01-01T{time}:00Z"))
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+
+        assert_eq!(window.remaining(at("23:00")), Some(Duration::from_secs(7 * 3600)));
+        assert_eq!(window.remaining(at("05:30")), Some(Duration::from_secs(1800)));
+        assert_eq!(window.remaining(at("06:00")), None);
+        assert_eq!(window.remaining(at("12:00")), None);
+
+        let window: MaintenanceWindow = serde_json::from_str(
+            r#"{"start": "01:00", "end": "05:00", "timezone": "+02:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(window.remaining(at("00:00")), Some(Duration::from_secs(3 * 3600)));
+        assert_eq!(window.remaining(at("03:00")), None);
+
+        assert!(serde_json::from_str::<MaintenanceWindow>(r#"{"start": "25:00", "end": "05:00"}"#).is_err());
+        assert!(
+            serde_json::from_str::<MaintenanceWindow>(
+                r#"{"start": "01:00", "end": "05:00", "timezone": "Mars/Olympus"}"#
+            )
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_deadline_settings() {
+        let data: &str = r#"{
+            "databases": [
+                {"driver": "postgres", "host": "localhost", "port": "5432", "username": "postgres",
+                 "password": "password", "schema": "public", "max_duration": 600},
+                {"driver": "mysql", "host": "localhost", "port": "3306", "username": "root",
+                 "password": "password", "schema": "app"}
+            ],
+            "maintenance_window": {"start": "01:00", "end": "05:00"},
+            "max_duration": "2h",
+            "cancel_on_deadline": true
+        }"#;
+        let config: CleanerConfig = serde_json::from_str(data).unwrap();
+
+        let first: DatabaseConfig = config.resolve(&config.databases[0]);
+        assert_eq!(first.max_duration, Some(Duration::from_secs(600)));
+        assert_eq!(first.maintenance_window, config.maintenance_window);
+        assert!(first.cancel_on_deadline);
+
+        let second: DatabaseConfig = config.resolve(&config.databases[1]);
+        assert_eq!(second.max_duration, Some(Duration::from_secs(7200)));
+        assert!(!second.dry_run);
+        assert!(!second.online_only);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_force_online() {
+        let data: &str = r#"{
+            "databases": [
+                {"driver": "postgres", "host": "localhost", "port": "5432", "username": "postgres",
+                 "password": "password", "schema": "public", "postgres": {"force_online": true}}
+            ]
+        }"#;
+        let config: CleanerConfig = serde_json::from_str(data).unwrap();
+        assert!(!config.databases[0].online_only);
+        assert!(config.resolve(&config.databases[0]).online_only);
+    }
+
+    #[tokio::test]
+    async fn test_deserialize_bloat_thresholds() {
+        let thresholds: BloatThresholds =
+            serde_json::from_str(r#"{"min_bloat_ratio": 0.5, "min_wasted_bytes": 1048576}"#).unwrap();
+        assert_eq!(thresholds.min_bloat_ratio, 0.5);
+        assert_eq!(thresholds.min_wasted_bytes, 1_048_576);
+        assert_eq!(thresholds.min_dead_tuple_ratio, BloatThresholds::default().min_dead_tuple_ratio);
+    }
 }
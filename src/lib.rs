@@ -1,5 +1,8 @@
 pub mod cleaner;
+pub mod cli;
 pub mod colors;
 pub mod config;
 pub mod helpers;
-pub mod logger;
\ No newline at end of file
+pub mod logger;
+pub mod output;
+pub mod quote;
\ No newline at end of file
@@ -1,6 +1,8 @@
 use crate::colors::{RED, RESET, YELLOW};
+use std::cell::RefCell;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Mutex, MutexGuard};
 
 /// Enum representing different types of logs
@@ -75,12 +77,73 @@ pub fn log_message(message: &str, log_type: &LogType) {
     logger.log(message, log_type);
 }
 
+/// When set, console messages are written to stderr so stdout only carries machine-readable output
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Reserve stdout for machine-readable output, console messages are then written to stderr
+pub fn reserve_stdout(reserved: bool) {
+    STDOUT_RESERVED.store(reserved, Ordering::Relaxed);
+}
+
+/// Check if stdout is reserved for machine-readable output
+#[must_use]
+pub fn is_stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+tokio::task_local! {
+    /// Console messages of the current task, as `(to_stderr, message)`, printed together when it ends
+    static GROUPED_OUTPUT: RefCell<Vec<(bool, String)>>;
+}
+
+/// Run a future and print all its console messages at once when it completes,
+/// so the output of tasks running concurrently is not interleaved
+pub async fn group_output<F: Future>(future: F) -> F::Output {
+    GROUPED_OUTPUT
+        .scope(RefCell::new(Vec::new()), async {
+            let output = future.await;
+            let lines: Vec<(bool, String)> = GROUPED_OUTPUT.with(RefCell::take);
+            let mut stdout = std::io::stdout().lock();
+            let mut stderr = std::io::stderr().lock();
+            for (to_stderr, message) in lines {
+                if to_stderr {
+                    writeln!(stderr, "{message}").ok();
+                } else {
+                    writeln!(stdout, "{message}").ok();
+                }
+            }
+            output
+        })
+        .await
+}
+
+/// Write a message to the console, or to the output of the current task when it is grouped
+fn write_console(message: &str, to_stderr: bool) {
+    let grouped: bool = GROUPED_OUTPUT
+        .try_with(|lines| lines.borrow_mut().push((to_stderr, message.to_string())))
+        .is_ok();
+
+    if grouped {
+        return;
+    }
+    if to_stderr {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
+
+/// Print a message to the console
+pub fn print_message(message: &str) {
+    write_console(message, is_stdout_reserved());
+}
+
 /// Static function to log a message and print it to the console
 pub fn log_and_print(message: &str, log_type: &LogType) {
     match log_type {
-        LogType::Critical | LogType::Error => eprintln!("{RED}{message}{RESET}"),
-        LogType::Warning => println!("{YELLOW}{message}{RESET}"),
-        LogType::Info => println!("{message}"),
+        LogType::Critical | LogType::Error => write_console(&format!("{RED}{message}{RESET}"), true),
+        LogType::Warning => print_message(&format!("{YELLOW}{message}{RESET}")),
+        LogType::Info => print_message(message),
     }
     log_message(message, log_type);
 }
@@ -105,6 +168,17 @@ mod tests {
         assert_eq!(format!("{}", LogType::Critical), "CRITICAL");
     }
 
+    #[tokio::test]
+    async fn test_group_output() {
+        let value = group_output(async {
+            print_message("grouped");
+            GROUPED_OUTPUT.with(|lines| lines.borrow().clone())
+        })
+        .await;
+        assert_eq!(value, vec![(false, String::from("grouped"))]);
+        assert!(GROUPED_OUTPUT.try_with(|_| ()).is_err());
+    }
+
     #[tokio::test]
     async fn test_logger_creation() {
         let _logger = Logger::new("test.log");
@@ -1,19 +1,49 @@
 use libcleaner::cleaner::database_cleaner::DatabaseCleaner;
 use libcleaner::cleaner::mysql::MySQLCleaner;
 use libcleaner::cleaner::postgres::PostgresCleaner;
+use libcleaner::cleaner::report::{CleanReport, StepStatus};
+use libcleaner::cli::{CliArgs, Command, USAGE};
 use libcleaner::colors::{BLUE, GREEN, RED, RESET, YELLOW};
 use libcleaner::config::{CleanerConfig, ConnectionEngine, DatabaseConfig};
-use libcleaner::logger::{log_and_print, LogType};
+use libcleaner::logger::{
+    group_output, is_stdout_reserved, log_and_print, print_message, reserve_stdout, LogType,
+};
+use libcleaner::output::{database_events, summary_event, OutputFormat, RunReport, RunSummary};
+use num_format::{Locale, ToFormattedString};
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::sync::Arc;
 use std::time::Instant;
-
-const CONFIG_FILE: &str = "cleaner.json";
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinSet};
 
 #[tokio::main]
 async fn main() {
     let start = Instant::now();
 
-    let cleaner_config = match load_configuration() {
+    let args = match CliArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{RED}{e}{RESET}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    match args.command {
+        Command::Help => {
+            println!("{USAGE}");
+            return;
+        }
+        Command::Version => {
+            println!("DBMSCleaner {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        _ => {}
+    }
+
+    reserve_stdout(args.output.is_machine_readable());
+
+    let cleaner_config = match load_configuration(&args) {
         Ok(config) => config,
         Err(e) => {
             log_and_print(&e, &LogType::Critical);
@@ -21,52 +51,97 @@ async fn main() {
         }
     };
 
+    match args.command {
+        Command::ValidateConfig => {
+            print_message(&format!(
+                "{GREEN}✓ Configuration {} is valid ({} database(s)){RESET}",
+                args.config_path,
+                cleaner_config.databases.len()
+            ));
+        }
+        Command::List => display_database_list(&cleaner_config),
+        Command::Inspect => {
+            display_header();
+            let failed_count = inspect_databases(&cleaner_config).await;
+            if failed_count > 0 {
+                std::process::exit(1);
+            }
+        }
+        Command::Clean | Command::Plan => run_clean(&cleaner_config, args.output, start).await,
+        Command::Help | Command::Version => unreachable!(),
+    }
+}
+
+/// Run the cleaning process on every configured database
+async fn run_clean(cleaner_config: &CleanerConfig, output: OutputFormat, start: Instant) {
+    let started_at: String = chrono::Local::now().to_rfc3339();
+
     display_header();
-    display_database_list(&cleaner_config);
+    display_database_list(cleaner_config);
 
     if cleaner_config.dry_run {
-        println!("\n{YELLOW}🔍 DRY RUN MODE - No changes will be made{RESET}");
+        print_message(&format!("\n{YELLOW}🔍 DRY RUN MODE - No changes will be made{RESET}"));
     }
 
-    if !confirm_operation(&cleaner_config) {
-        println!("{RED}❌ Operation cancelled by user{RESET}");
+    if !confirm_operation(cleaner_config) {
+        print_message(&format!("{RED}❌ Operation cancelled by user{RESET}"));
         return;
     }
 
-    println!("\n{GREEN}✓ Starting cleanup operations...{RESET}\n");
+    print_message(&format!("\n{GREEN}✓ Starting cleanup operations...{RESET}\n"));
 
-    let (success_count, failed_count) = process_databases(&cleaner_config).await;
+    let (reports, failed_count) = process_databases(cleaner_config, output).await;
 
-    display_summary(&cleaner_config, success_count, failed_count, start.elapsed());
+    display_summary(cleaner_config, &reports, failed_count, start.elapsed());
+
+    match output {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            let run = RunReport::new(started_at, cleaner_config.dry_run, &reports, start.elapsed());
+            match serde_json::to_string_pretty(&run) {
+                Ok(json) => println!("{json}"),
+                Err(e) => log_and_print(&format!("Failed to serialize the report: {e}"), &LogType::Error),
+            }
+        }
+        OutputFormat::Ndjson => {
+            println!("{}", summary_event(&RunSummary::new(&reports, start.elapsed())));
+        }
+    }
+
+    if failed_count > 0 {
+        std::process::exit(1);
+    }
 }
 
-/// Load the configuration from the file
-fn load_configuration() -> Result<CleanerConfig, String> {
-    CleanerConfig::from_file(CONFIG_FILE).map_err(|e| format!("{e}"))
+/// Load the configuration from the file and apply the command-line overrides
+fn load_configuration(args: &CliArgs) -> Result<CleanerConfig, String> {
+    let mut config = CleanerConfig::from_file(&args.config_path).map_err(|e| format!("{e}"))?;
+    args.apply(&mut config)?;
+    Ok(config)
 }
 
 /// Display the application header
 fn display_header() {
-    println!("{BLUE}╔══════════════════════════════════════════════════════╗{RESET}");
-    println!("{BLUE}║{RESET}          DBMSCleaner - Database Optimizer          {BLUE}║{RESET}");
-    println!("{BLUE}╚══════════════════════════════════════════════════════╝{RESET}\n");
+    print_message(&format!("{BLUE}╔══════════════════════════════════════════════════════╗{RESET}"));
+    print_message(&format!("{BLUE}║{RESET}          DBMSCleaner - Database Optimizer          {BLUE}║{RESET}"));
+    print_message(&format!("{BLUE}╚══════════════════════════════════════════════════════╝{RESET}\n"));
 }
 
 /// Display the list of databases to be cleaned
 fn display_database_list(config: &CleanerConfig) {
-    println!("Found {} database(s) to clean:", config.databases.len());
+    print_message(&format!("Found {} database(s) to clean:", config.databases.len()));
 
     for (i, db) in config.databases.iter().enumerate() {
         let db_name = get_database_name(db, i);
-        println!(
+        print_message(&format!(
             "  {}. {BLUE}{}{RESET} ({}) - {}:{}/{}",
             i + 1,
             db_name,
             db.driver,
             db.host,
             db.port,
-            db.schema
-        );
+            db.target()
+        ));
     }
 }
 
@@ -83,10 +158,15 @@ fn confirm_operation(config: &CleanerConfig) -> bool {
         return true;
     }
 
-    println!("\n{YELLOW}⚠️  WARNING: This will perform optimization operations on the databases.{RESET}");
-    println!("{YELLOW}   Operations include: VACUUM, REINDEX, OPTIMIZE, LOG PURGING{RESET}");
-    print!("\nDo you want to continue? (yes/no): ");
-    io::stdout().flush().unwrap();
+    print_message(&format!("\n{YELLOW}⚠️  WARNING: This will perform optimization operations on the databases.{RESET}"));
+    print_message(&format!("{YELLOW}   Operations include: VACUUM, REINDEX, OPTIMIZE, LOG PURGING{RESET}"));
+    if is_stdout_reserved() {
+        eprint!("\nDo you want to continue? (yes/no): ");
+        io::stderr().flush().unwrap();
+    } else {
+        print!("\nDo you want to continue? (yes/no): ");
+        io::stdout().flush().unwrap();
+    }
 
     let mut input = String::new();
     io::stdin()
@@ -97,54 +177,153 @@ fn confirm_operation(config: &CleanerConfig) -> bool {
     matches!(input.as_str(), "yes" | "y")
 }
 
-/// Process all databases and return success/failure counts
-async fn process_databases(config: &CleanerConfig) -> (usize, usize) {
-    let mut success_count = 0;
-    let mut failed_count = 0;
+/// Process all databases, at most `max_parallel_databases` at a time,
+/// and return the report of each database in configuration order with the failure count
+async fn process_databases(config: &CleanerConfig, output: OutputFormat) -> (Vec<CleanReport>, usize) {
+    let semaphore: Arc<Semaphore> = Arc::new(Semaphore::new(config.max_parallel_databases));
+    let grouped: bool = config.max_parallel_databases > 1;
+    let mut tasks: JoinSet<(usize, CleanReport)> = JoinSet::new();
+    let mut task_indexes: HashMap<task::Id, usize> = HashMap::with_capacity(config.databases.len());
+    let mut reports: Vec<Option<CleanReport>> = vec![None; config.databases.len()];
 
     for (i, db_config) in config.databases.iter().enumerate() {
+        while let Some(result) = tasks.try_join_next_with_id() {
+            collect_task_result(config, result, &task_indexes, &mut reports, output);
+        }
+
+        let Ok(permit) = Arc::clone(&semaphore).acquire_owned().await else {
+            break;
+        };
+
         let db_name = get_database_name(db_config, i);
+        let db_config: DatabaseConfig = config.resolve(db_config);
 
-        print_database_header(&db_name);
+        let handle = tasks.spawn(async move {
+            let report = if grouped {
+                group_output(process_database(&db_config, &db_name)).await
+            } else {
+                process_database(&db_config, &db_name).await
+            };
+            drop(permit);
+            (i, report)
+        });
+        task_indexes.insert(handle.id(), i);
+    }
+
+    while let Some(result) = tasks.join_next_with_id().await {
+        collect_task_result(config, result, &task_indexes, &mut reports, output);
+    }
+
+    let reports: Vec<CleanReport> = reports.into_iter().flatten().collect();
+    let failed_count = reports.iter().filter(|report| !report.is_healthy()).count();
+    (reports, failed_count)
+}
 
-        if config.dry_run {
-            handle_dry_run(db_config, &db_name);
-            success_count += 1;
-            continue;
+/// Store the report of a finished database task, and emit its events in `ndjson` mode
+fn collect_task_result(
+    config: &CleanerConfig,
+    result: Result<(task::Id, (usize, CleanReport)), task::JoinError>,
+    task_indexes: &HashMap<task::Id, usize>,
+    reports: &mut [Option<CleanReport>],
+    output: OutputFormat,
+) {
+    let (index, report) = match result {
+        Ok((_, (index, report))) => (index, report),
+        Err(e) => {
+            let index = task_indexes[&e.id()];
+            let db_config = &config.databases[index];
+            let message = format!("Cleaning of {} was aborted: {e}", get_database_name(db_config, index));
+            log_and_print(&message, &LogType::Error);
+            let mut report = CleanReport::new(db_config);
+            report.name = Some(get_database_name(db_config, index));
+            report.error(message);
+            (index, report.finish())
         }
+    };
 
-        match process_single_database(db_config, &db_name).await {
-            Ok(()) => {
-                println!("{GREEN}✓ Successfully cleaned {}{RESET}\n", db_name);
-                success_count += 1;
-            }
-            Err(e) => {
-                log_and_print(&e, &LogType::Error);
-                failed_count += 1;
-            }
+    if output == OutputFormat::Ndjson {
+        for event in database_events(&report) {
+            println!("{event}");
         }
     }
+    reports[index] = Some(report);
+}
+
+/// Clean a single database, printing its progress, and return its report
+async fn process_database(db_config: &DatabaseConfig, db_name: &str) -> CleanReport {
+    print_database_header(db_name);
+
+    let mut report = match process_single_database(db_config, db_name).await {
+        Ok(report) if !report.is_healthy() => {
+            log_and_print(
+                &format!(
+                    "Cleaning of {} completed with {} failed step(s) and {} error(s)",
+                    db_name,
+                    report.count_steps(StepStatus::Failed),
+                    report.errors.len()
+                ),
+                &LogType::Error,
+            );
+            report
+        }
+        Ok(report) => {
+            let verb = if report.dry_run { "planned" } else { "cleaned" };
+            print_message(&format!("{GREEN}✓ Successfully {verb} {}{RESET}\n", db_name));
+            report
+        }
+        Err(e) => {
+            log_and_print(&e, &LogType::Error);
+            let mut report = CleanReport::new(db_config);
+            report.error(e);
+            report.finish()
+        }
+    };
 
-    (success_count, failed_count)
+    report.name.get_or_insert_with(|| db_name.to_string());
+    report
 }
 
 /// Print the database processing header
 fn print_database_header(db_name: &str) {
-    println!("{BLUE}═══════════════════════════════════════════════════════{RESET}");
-    println!("{BLUE}Processing: {}{RESET}", db_name);
-    println!("{BLUE}═══════════════════════════════════════════════════════{RESET}");
+    print_message(&format!("{BLUE}═══════════════════════════════════════════════════════{RESET}"));
+    print_message(&format!("{BLUE}Processing: {}{RESET}", db_name));
+    print_message(&format!("{BLUE}═══════════════════════════════════════════════════════{RESET}"));
 }
 
-/// Handle dry run mode for a database
-fn handle_dry_run(db_config: &DatabaseConfig, db_name: &str) {
-    println!(
-        "{YELLOW}[DRY RUN] Would clean {} ({}) at {}:{}/{}{RESET}",
-        db_name, db_config.driver, db_config.host, db_config.port, db_config.schema
-    );
+/// Inspect all databases and return the number of failures
+async fn inspect_databases(config: &CleanerConfig) -> usize {
+    let mut failed_count = 0;
+
+    for (i, db_config) in config.databases.iter().enumerate() {
+        let db_name = get_database_name(db_config, i);
+
+        print_database_header(&db_name);
+
+        // Same settings as the clean command, with the defaults of the cleaner configuration applied
+        let db_config: DatabaseConfig = config.resolve(db_config);
+        let result = match create_cleaner(&db_config, &db_name) {
+            Ok(cleaner) => cleaner
+                .inspect()
+                .await
+                .map_err(|e| format!("Failed to inspect {}: {}", db_name, e)),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            log_and_print(&e, &LogType::Error);
+            failed_count += 1;
+        }
+        print_message("");
+    }
+
+    failed_count
 }
 
 /// Process a single database
-async fn process_single_database(db_config: &DatabaseConfig, db_name: &str) -> Result<(), String> {
+async fn process_single_database(
+    db_config: &DatabaseConfig,
+    db_name: &str,
+) -> Result<CleanReport, String> {
     let cleaner = create_cleaner(db_config, db_name)?;
     cleaner
         .clean()
@@ -174,20 +353,32 @@ fn create_cleaner(
 /// Display the final summary
 fn display_summary(
     config: &CleanerConfig,
-    success_count: usize,
+    reports: &[CleanReport],
     failed_count: usize,
     elapsed: std::time::Duration,
 ) {
-    println!("{BLUE}═══════════════════════════════════════════════════════{RESET}");
-    println!("{BLUE}║{RESET}                    SUMMARY                        {BLUE}║{RESET}");
-    println!("{BLUE}═══════════════════════════════════════════════════════{RESET}");
-    println!("Total databases: {}", config.databases.len());
-    println!("{GREEN}✓ Successful: {}{RESET}", success_count);
+    let success_count = reports.iter().filter(|report| report.is_healthy()).count();
+    let warning_count: usize = reports.iter().map(CleanReport::warning_count).sum();
+    let reclaimed_bytes: i64 = reports.iter().map(CleanReport::reclaimed_bytes).sum();
+
+    print_message(&format!("{BLUE}═══════════════════════════════════════════════════════{RESET}"));
+    print_message(&format!("{BLUE}║{RESET}                    SUMMARY                        {BLUE}║{RESET}"));
+    print_message(&format!("{BLUE}═══════════════════════════════════════════════════════{RESET}"));
+    print_message(&format!("Total databases: {}", config.databases.len()));
+    print_message(&format!("{GREEN}✓ Successful: {}{RESET}", success_count));
 
     if failed_count > 0 {
-        println!("{RED}✗ Failed: {}{RESET}", failed_count);
+        print_message(&format!("{RED}✗ Failed: {}{RESET}", failed_count));
+    }
+
+    if warning_count > 0 {
+        print_message(&format!("{YELLOW}⚠ Warnings: {}{RESET}", warning_count));
     }
 
-    println!("Total time: {GREEN}{:?}{RESET}", elapsed);
-    println!("{BLUE}═══════════════════════════════════════════════════════{RESET}");
+    print_message(&format!(
+        "Space reclaimed: {GREEN}{}{RESET} bytes",
+        reclaimed_bytes.to_formatted_string(&Locale::en)
+    ));
+    print_message(&format!("Total time: {GREEN}{:?}{RESET}", elapsed));
+    print_message(&format!("{BLUE}═══════════════════════════════════════════════════════{RESET}"));
 }
@@ -0,0 +1,235 @@
+//! Machine-readable output of a cleaning run.
+//!
+//! In `json` mode a single document is written to stdout at the end of the run.
+//! In `ndjson` mode one event per line is written as soon as each database is done,
+//! followed by a final `summary` event. Human-readable messages go to stderr in both modes.
+
+use crate::cleaner::report::{CleanReport, StepStatus};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Format of the results written to stdout
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[repr(u8)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Parse an output format from its command-line name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "ndjson" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// Returns true if stdout must only carry machine-readable output
+    #[inline]
+    #[must_use]
+    pub const fn is_machine_readable(&self) -> bool {
+        !matches!(self, Self::Text)
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+            Self::Ndjson => write!(f, "ndjson"),
+        }
+    }
+}
+
+/// Totals of a cleaning run
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct RunSummary {
+    pub total_databases: usize,
+    pub successful: usize,
+    pub failed: usize,
+    pub warnings: usize,
+    pub start_bytes: i64,
+    pub end_bytes: i64,
+    pub reclaimed_bytes: i64,
+    pub duration_ms: u128,
+}
+
+impl RunSummary {
+    /// Compute the totals of the given reports
+    pub fn new(reports: &[CleanReport], elapsed: Duration) -> Self {
+        let successful = reports.iter().filter(|report| report.is_healthy()).count();
+        Self {
+            total_databases: reports.len(),
+            successful,
+            failed: reports.len() - successful,
+            warnings: reports.iter().map(CleanReport::warning_count).sum(),
+            start_bytes: reports.iter().map(|report| report.start_bytes).sum(),
+            end_bytes: reports.iter().map(|report| report.end_bytes).sum(),
+            reclaimed_bytes: reports.iter().map(CleanReport::reclaimed_bytes).sum(),
+            duration_ms: elapsed.as_millis(),
+        }
+    }
+}
+
+/// Complete result of a cleaning run, written in `json` mode
+#[derive(Serialize, Debug, Clone)]
+#[must_use]
+pub struct RunReport<'a> {
+    pub version: &'static str,
+    pub started_at: String,
+    pub dry_run: bool,
+    pub summary: RunSummary,
+    pub databases: &'a [CleanReport],
+}
+
+impl<'a> RunReport<'a> {
+    /// Build the report of a run from the report of each database
+    pub fn new(
+        started_at: String,
+        dry_run: bool,
+        reports: &'a [CleanReport],
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            started_at,
+            dry_run,
+            summary: RunSummary::new(reports, elapsed),
+            databases: reports,
+        }
+    }
+}
+
+/// Build the `ndjson` events of a database: one per step, then one for the database itself
+#[must_use]
+pub fn database_events(report: &CleanReport) -> Vec<Value> {
+    let database = report.name.as_deref().unwrap_or_default();
+    let mut events: Vec<Value> = Vec::with_capacity(report.steps.len() + 1);
+
+    for step in &report.steps {
+        let mut event = json!({ "event": "step", "database": database });
+        if let (Value::Object(event), Ok(Value::Object(step))) =
+            (&mut event, serde_json::to_value(step))
+        {
+            event.extend(step);
+        }
+        events.push(event);
+    }
+
+    events.push(json!({
+        "event": "database",
+        "database": database,
+        "driver": report.driver,
+        "host": report.host,
+        "dry_run": report.dry_run,
+        "healthy": report.is_healthy(),
+        "start_bytes": report.start_bytes,
+        "end_bytes": report.end_bytes,
+        "reclaimed_bytes": report.reclaimed_bytes(),
+        "duration_ms": report.duration_ms,
+        "failed_steps": report.count_steps(StepStatus::Failed),
+        "warnings": report.warnings,
+        "errors": report.errors,
+    }));
+
+    events
+}
+
+/// Build the final `ndjson` event of a run
+#[must_use]
+pub fn summary_event(summary: &RunSummary) -> Value {
+    let mut event = json!({ "event": "summary" });
+    if let (Value::Object(event), Ok(Value::Object(summary))) =
+        (&mut event, serde_json::to_value(summary))
+    {
+        event.extend(summary);
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleaner::report::StepReport;
+    use crate::config::{ConnectionEngine, Operation, tests::get_test_config};
+
+    fn get_test_report(name: &str, start_bytes: i64, end_bytes: i64) -> CleanReport {
+        let mut config = get_test_config(ConnectionEngine::Postgres, "5432");
+        config.name = Some(name.to_string());
+        let mut report = CleanReport::new(&config);
+        report.start_bytes = start_bytes;
+        report.end_bytes = end_bytes;
+        let mut step = StepReport::new(Operation::Vacuum, Some("app"));
+        step.add_table("public.users", Duration::from_millis(5), Ok(()));
+        report.add_step(step.finish(Ok(())));
+        report.finish()
+    }
+
+    #[test]
+    fn test_output_format_from_name() {
+        assert_eq!(OutputFormat::from_name("text"), Some(OutputFormat::Text));
+        assert_eq!(OutputFormat::from_name("JSON"), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::from_name("ndjson"), Some(OutputFormat::Ndjson));
+        assert_eq!(OutputFormat::from_name("xml"), None);
+        assert!(!OutputFormat::Text.is_machine_readable());
+        assert!(OutputFormat::Json.is_machine_readable());
+        assert_eq!(OutputFormat::Ndjson.to_string(), "ndjson");
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let mut failed = get_test_report("second", 100, 100);
+        failed.error("connection refused");
+        let reports = vec![get_test_report("first", 1000, 400), failed];
+
+        let summary = RunSummary::new(&reports, Duration::from_millis(42));
+        assert_eq!(summary.total_databases, 2);
+        assert_eq!(summary.successful, 1);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.start_bytes, 1100);
+        assert_eq!(summary.reclaimed_bytes, 600);
+        assert_eq!(summary.duration_ms, 42);
+    }
+
+    #[test]
+    fn test_run_report_serialize() {
+        let reports = vec![get_test_report("first", 1000, 400)];
+        let run = RunReport::new(String::from("2024-01-01T00:00:00Z"), true, &reports, Duration::ZERO);
+        let json: Value = serde_json::to_value(&run).unwrap();
+
+        assert_eq!(json["dry_run"], true);
+        assert_eq!(json["summary"]["reclaimed_bytes"], 600);
+        assert_eq!(json["databases"][0]["name"], "first");
+        assert_eq!(json["databases"][0]["steps"][0]["operation"], "vacuum");
+        assert_eq!(json["databases"][0]["steps"][0]["tables"][0]["table"], "public.users");
+    }
+
+    #[test]
+    fn test_database_events() {
+        let report = get_test_report("first", 1000, 400);
+        let events = database_events(&report);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["event"], "step");
+        assert_eq!(events[0]["database"], "first");
+        assert_eq!(events[0]["operation"], "vacuum");
+        assert_eq!(events[0]["target"], "app");
+        assert_eq!(events[1]["event"], "database");
+        assert_eq!(events[1]["reclaimed_bytes"], 600);
+        assert_eq!(events[1]["healthy"], true);
+
+        let summary = summary_event(&RunSummary::new(&[report], Duration::ZERO));
+        assert_eq!(summary["event"], "summary");
+        assert_eq!(summary["successful"], 1);
+    }
+}
@@ -0,0 +1,89 @@
+/// SQL dialect used to quote the identifiers and literals spliced into generated statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Dialect {
+    MySql,
+    Postgres,
+}
+
+impl Dialect {
+    /// Quote an identifier, doubling the quote character it may contain
+    /// `MySQL` uses backquotes and `PostgreSQL` double quotes, which also keeps mixed-case names intact
+    #[must_use]
+    pub fn quote_identifier(self, name: &str) -> String {
+        let quote: char = match self {
+            Self::MySql => '`',
+            Self::Postgres => '"',
+        };
+        let mut quoted: String = String::with_capacity(name.len() + 2);
+        quoted.push(quote);
+        for c in name.chars() {
+            if c == quote {
+                quoted.push(quote);
+            }
+            quoted.push(c);
+        }
+        quoted.push(quote);
+        quoted
+    }
+
+    /// Quote a schema-qualified name, `schema`.`name`
+    #[must_use]
+    pub fn quote_qualified(self, schema: &str, name: &str) -> String {
+        format!("{}.{}", self.quote_identifier(schema), self.quote_identifier(name))
+    }
+
+    /// Quote a string literal, for the statements that cannot take bind parameters
+    /// Backslashes are escapes in `MySQL` strings, and in `PostgreSQL` only with the E'' syntax
+    #[must_use]
+    pub fn quote_literal(self, value: &str) -> String {
+        let escaped: String = value.replace('\'', "''");
+        match self {
+            Self::MySql => format!("'{}'", escaped.replace('\\', "\\\\")),
+            Self::Postgres if value.contains('\\') => format!("E'{}'", escaped.replace('\\', "\\\\")),
+            Self::Postgres => format!("'{escaped}'"),
+        }
+    }
+
+    /// Comma-separated list of `count` bind placeholders, `?` for `MySQL` and `$1, $2...` for `PostgreSQL`
+    #[must_use]
+    pub fn placeholders(self, count: usize) -> String {
+        (1..=count)
+            .map(|i| match self {
+                Self::MySql => String::from("?"),
+                Self::Postgres => format!("${i}"),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(Dialect::Postgres.quote_identifier("Orders"), "\"Orders\"");
+        assert_eq!(Dialect::Postgres.quote_identifier("my\"table"), "\"my\"\"table\"");
+        assert_eq!(Dialect::MySql.quote_identifier("orders"), "`orders`");
+        assert_eq!(Dialect::MySql.quote_identifier("my`table"), "`my``table`");
+        assert_eq!(Dialect::MySql.quote_qualified("app", "orders"), "`app`.`orders`");
+        assert_eq!(Dialect::Postgres.quote_qualified("App", "order items"), "\"App\".\"order items\"");
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(Dialect::Postgres.quote_literal("tx-1"), "'tx-1'");
+        assert_eq!(Dialect::Postgres.quote_literal("it's"), "'it''s'");
+        assert_eq!(Dialect::Postgres.quote_literal("a\\b'"), "E'a\\\\b'''");
+        assert_eq!(Dialect::MySql.quote_literal("a\\b'"), "'a\\\\b'''");
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(Dialect::MySql.placeholders(3), "?, ?, ?");
+        assert_eq!(Dialect::Postgres.placeholders(2), "$1, $2");
+        assert_eq!(Dialect::MySql.placeholders(0), "");
+    }
+}